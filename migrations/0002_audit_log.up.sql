@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS audit_log (
+  audit_id BIGINT UNSIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+  action VARCHAR(31) NOT NULL,
+  actor VARCHAR(127) NOT NULL,
+  request_id VARCHAR(63) NOT NULL,
+  table_number INT UNSIGNED NOT NULL,
+  item_id INT UNSIGNED NOT NULL,
+  before_snapshot JSON NULL,
+  after_snapshot JSON NULL,
+  recorded_on DATETIME(6) NOT NULL
+) ENGINE = InnoDB CHARACTER SET = utf8mb4 COLLATE = utf8mb4_0900_ai_ci;
+
+CREATE INDEX index_on_audit_table_item ON audit_log (table_number, item_id);
+CREATE INDEX index_on_audit_recorded_on ON audit_log (recorded_on);
@@ -0,0 +1,5 @@
+DROP INDEX index_on_voided_on ON table_items;
+
+ALTER TABLE table_items
+  DROP COLUMN void_reason,
+  DROP COLUMN voided_on;
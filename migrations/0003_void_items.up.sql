@@ -0,0 +1,5 @@
+ALTER TABLE table_items
+  ADD COLUMN voided_on DATETIME NULL,
+  ADD COLUMN void_reason VARCHAR(31) NULL;
+
+CREATE INDEX index_on_voided_on ON table_items (voided_on);
@@ -0,0 +1 @@
+ALTER TABLE table_items DROP COLUMN version;
@@ -0,0 +1 @@
+ALTER TABLE table_items ADD COLUMN version INT UNSIGNED NOT NULL DEFAULT 1;
@@ -0,0 +1,3 @@
+DROP INDEX index_on_table_number_ordered_on ON table_items;
+
+ALTER TABLE table_items DROP COLUMN status;
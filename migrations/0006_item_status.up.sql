@@ -0,0 +1,3 @@
+ALTER TABLE table_items ADD COLUMN status VARCHAR(15) NOT NULL DEFAULT 'ordered';
+
+CREATE INDEX index_on_table_number_ordered_on ON table_items (table_number, ordered_on);
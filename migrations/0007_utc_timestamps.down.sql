@@ -0,0 +1,11 @@
+ALTER TABLE table_items
+  MODIFY ordered_on DATETIME NOT NULL,
+  MODIFY voided_on DATETIME NULL;
+ALTER TABLE audit_log MODIFY recorded_on DATETIME(6) NOT NULL;
+ALTER TABLE idempotency_keys MODIFY created_on DATETIME NOT NULL;
+
+UPDATE table_items SET
+  ordered_on = CONVERT_TZ(ordered_on, '+00:00', @restaurant_timezone),
+  voided_on = CONVERT_TZ(voided_on, '+00:00', @restaurant_timezone);
+UPDATE audit_log SET recorded_on = CONVERT_TZ(recorded_on, '+00:00', @restaurant_timezone);
+UPDATE idempotency_keys SET created_on = CONVERT_TZ(created_on, '+00:00', @restaurant_timezone);
@@ -0,0 +1,13 @@
+-- Rows written before this migration hold the restaurant's local time. `migrate` sets
+-- @restaurant_timezone from RESTAURANT_TIMEZONE and checks that MySQL can convert from it.
+UPDATE table_items SET
+  ordered_on = CONVERT_TZ(ordered_on, @restaurant_timezone, '+00:00'),
+  voided_on = CONVERT_TZ(voided_on, @restaurant_timezone, '+00:00');
+UPDATE audit_log SET recorded_on = CONVERT_TZ(recorded_on, @restaurant_timezone, '+00:00');
+UPDATE idempotency_keys SET created_on = CONVERT_TZ(created_on, @restaurant_timezone, '+00:00');
+
+ALTER TABLE table_items
+  MODIFY ordered_on DATETIME(6) NOT NULL COMMENT 'UTC',
+  MODIFY voided_on DATETIME(6) NULL COMMENT 'UTC';
+ALTER TABLE audit_log MODIFY recorded_on DATETIME(6) NOT NULL COMMENT 'UTC';
+ALTER TABLE idempotency_keys MODIFY created_on DATETIME NOT NULL COMMENT 'UTC';
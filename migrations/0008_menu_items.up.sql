@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS menu_items (
+  item_name VARCHAR(127) PRIMARY KEY NOT NULL,
+  prepare_minutes INT UNSIGNED NOT NULL,
+  updated_on DATETIME(6) NOT NULL COMMENT 'UTC'
+) ENGINE = InnoDB CHARACTER SET = utf8mb4 COLLATE = utf8mb4_0900_ai_ci;
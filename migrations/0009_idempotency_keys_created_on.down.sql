@@ -0,0 +1 @@
+ALTER TABLE idempotency_keys DROP INDEX idempotency_keys_created_on;
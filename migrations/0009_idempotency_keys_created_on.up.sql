@@ -0,0 +1 @@
+ALTER TABLE idempotency_keys ADD INDEX idempotency_keys_created_on (created_on);
@@ -1,14 +1,2 @@
-DROP DATABASE IF EXISTS simple_restaurant;
-CREATE DATABASE simple_restaurant;
-USE simple_restaurant;
-
-CREATE TABLE IF NOT EXISTS table_items (
-  item_id INT UNSIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
-  table_number INT UNSIGNED NOT NULL,
-  item_name VARCHAR(127) NOT NULL,
-  prepare_minutes INT UNSIGNED NOT NULL,
-  ordered_on DATETIME NOT NULL
-) ENGINE = InnoDB CHARACTER SET = utf8mb4 COLLATE = utf8mb4_0900_ai_ci;
-
-CREATE INDEX index_on_table_number ON table_items (table_number);
-
+-- The schema is created by `simple-restaurant-api migrate up`, see `migrations/`.
+CREATE DATABASE IF NOT EXISTS simple_restaurant;
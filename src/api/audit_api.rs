@@ -0,0 +1,40 @@
+use actix_request_identifier::RequestId;
+use actix_web::{web, HttpResponse, Responder};
+use mysql::Pool;
+
+use crate::common::errors::{AppError, ProblemDetails};
+use crate::common::telemetry;
+use crate::model::requests::AuditQuery;
+use crate::model::resources::AuditEntryResource;
+use crate::model::time::RestaurantTimezone;
+use crate::persist::audit_log::get_audit_entries;
+
+#[utoipa::path(
+    get,
+    path = "/audit",
+    context_path = "/v1",
+    tag = "audit",
+    params(AuditQuery),
+    responses(
+        (status = 200, description = "Most recent entries first", body = Vec<AuditEntryResource>),
+        (status = 400, body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub(crate) async fn get_audit_log(
+    query: web::Query<AuditQuery>,
+    data: web::Data<Pool>,
+    timezone: web::Data<RestaurantTimezone>,
+    request_id: RequestId,
+) -> Result<impl Responder, AppError> {
+    let audit_query = query.into_inner();
+    let timezone = **timezone;
+
+    let entries = telemetry::block(move ||
+        get_audit_entries(&data, request_id, timezone, audit_query)
+    ).await??;
+
+    let entries: Vec<AuditEntryResource> = entries.into_iter()
+        .map(|entry| AuditEntryResource::new(&timezone, entry))
+        .collect();
+    Ok(HttpResponse::Ok().json(entries))
+}
@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use actix_web::http::header::{CacheControl, CacheDirective};
+use actix_web::http::StatusCode;
+use actix_web::{get, web, HttpResponse, Responder};
+use mysql::Pool;
+
+use crate::common::errors::AppError;
+use crate::common::shutdown::Shutdown;
+use crate::model::responses::{DependencyCheck, HealthResponse, HealthStatus};
+use crate::persist::health::ping;
+use crate::persist::migrations::{schema_state, SchemaState};
+
+/// Longer than a healthy pool ever needs, shorter than the orchestrator's probe timeout.
+const READINESS_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The process is up and serving requests; dependencies are not checked.
+#[utoipa::path(
+    tag = "health",
+    responses((status = 200, body = HealthResponse))
+)]
+#[get("/health/live")]
+pub(crate) async fn live() -> impl Responder {
+    health_response(BTreeMap::new())
+}
+
+/// Requests can be served: the server is not shutting down, a pool connection answers and
+/// the schema is migrated.
+#[utoipa::path(
+    tag = "health",
+    responses(
+        (status = 200, body = HealthResponse),
+        (status = 503, body = HealthResponse, description = "A dependency is down"),
+    )
+)]
+#[get("/health/ready")]
+pub(crate) async fn ready(data: web::Data<Pool>, shutdown: web::Data<Shutdown>) -> Result<impl Responder, AppError> {
+    if shutdown.is_draining() {
+        // The pool is left to the requests being drained.
+        let started = Instant::now();
+        let server = check(HealthStatus::Down, started, Some("shutting down".into()));
+        return Ok(health_response(BTreeMap::from([("server".to_string(), server)])));
+    }
+    let checks = web::block(move || check_dependencies(&data)).await?;
+    Ok(health_response(checks))
+}
+
+fn check_dependencies(pool: &Pool) -> BTreeMap<String, DependencyCheck> {
+    let started = Instant::now();
+    let conn = ping(pool, READINESS_TIMEOUT);
+    let database = match conn {
+        Ok(_) => check(HealthStatus::Up, started, None),
+        Err(_) => check(HealthStatus::Down, started, Some("no connection answered".into())),
+    };
+
+    let started = Instant::now();
+    let schema = match conn.map(|mut conn| schema_state(&mut conn)) {
+        Ok(Ok(SchemaState::UpToDate)) => check(HealthStatus::Up, started, None),
+        Ok(Ok(SchemaState::Ahead { current, expected })) => check(HealthStatus::Up, started,
+            Some(format!("version {current} is newer than the expected {expected}"))),
+        Ok(Ok(SchemaState::Outdated { current, expected })) => check(HealthStatus::Down, started,
+            Some(format!("version {current}, expected {expected}"))),
+        Ok(Ok(SchemaState::Uninitialized { expected })) => check(HealthStatus::Down, started,
+            Some(format!("schema not ready, no migration applied, expected version {expected}"))),
+        Ok(Err(_)) => check(HealthStatus::Down, started, Some("version can not be read".into())),
+        Err(_) => check(HealthStatus::Down, started, Some("database is down".into())),
+    };
+
+    BTreeMap::from([("database".to_string(), database), ("schema".to_string(), schema)])
+}
+
+fn check(status: HealthStatus, started: Instant, detail: Option<String>) -> DependencyCheck {
+    // Microsecond resolution is plenty for a probe.
+    let latency_ms = (started.elapsed().as_secs_f64() * 1_000_000.0).round() / 1000.0;
+    DependencyCheck { status, latency_ms, detail }
+}
+
+fn health_response(checks: BTreeMap<String, DependencyCheck>) -> HttpResponse {
+    let status = if checks.values().all(|check| check.status == HealthStatus::Up) {
+        HealthStatus::Up
+    } else {
+        HealthStatus::Down
+    };
+    let status_code = match status {
+        HealthStatus::Up => StatusCode::OK,
+        HealthStatus::Down => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    HttpResponse::build(status_code)
+        .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .json(HealthResponse { status, checks })
+}
@@ -0,0 +1,19 @@
+use actix_web::http::header::ContentType;
+use actix_web::{get, HttpResponse, Responder};
+
+use crate::common::metrics::METRICS;
+
+/// Prometheus text format version 0.0.4.
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Request, database pool, persistence and order metrics in the Prometheus text format.
+#[utoipa::path(
+    tag = "metrics",
+    responses((status = 200, content_type = "text/plain", body = String))
+)]
+#[get("/metrics")]
+pub(crate) async fn metrics() -> impl Responder {
+    HttpResponse::Ok()
+        .insert_header(ContentType(CONTENT_TYPE.parse().unwrap()))
+        .body(METRICS.render())
+}
@@ -1 +1,7 @@
-pub mod restaurant_api;
\ No newline at end of file
+pub mod audit_api;
+pub mod health_api;
+pub mod metrics_api;
+pub mod openapi;
+pub mod report_api;
+pub mod restaurant_api;
+pub mod restaurant_api_v2;
\ No newline at end of file
@@ -0,0 +1,50 @@
+use actix_web::{get, HttpResponse, Responder};
+use utoipa::OpenApi;
+
+use crate::api::{audit_api, health_api, metrics_api, report_api, restaurant_api, restaurant_api_v2};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Simple Restaurant API",
+        description = "Orders of the restaurant tables. The unversioned paths are aliases of `/v1` kept for deployed tablets and are not documented here.",
+    ),
+    paths(
+        restaurant_api::add_items,
+        restaurant_api::get_all_items,
+        restaurant_api::get_table,
+        restaurant_api::get_item,
+        restaurant_api::remove_item,
+        restaurant_api::restore_item,
+        restaurant_api::bulk_void_items,
+        restaurant_api::bulk_update_status,
+        audit_api::get_audit_log,
+        report_api::get_voided_items_report,
+        restaurant_api_v2::add_items,
+        restaurant_api_v2::get_all_items,
+        restaurant_api_v2::get_table,
+        restaurant_api_v2::get_item,
+        restaurant_api_v2::remove_item,
+        restaurant_api_v2::restore_item,
+        restaurant_api_v2::get_audit_log,
+        restaurant_api_v2::get_voided_items_report,
+        health_api::live,
+        health_api::ready,
+        metrics_api::metrics,
+    ),
+    tags(
+        (name = "tables"),
+        (name = "items"),
+        (name = "audit"),
+        (name = "reports"),
+        (name = "health", description = "Liveness and readiness probes, outside of the versioned API"),
+        (name = "metrics", description = "Prometheus metrics, outside of the versioned API"),
+        (name = "v2", description = "Tables, items, audit log and reports with RFC 3339 timestamps; bulk endpoints are shared with /v1"),
+    )
+)]
+pub struct ApiDoc;
+
+#[get("/openapi.json")]
+pub(crate) async fn get_openapi() -> impl Responder {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
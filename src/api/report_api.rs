@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+use actix_request_identifier::RequestId;
+use actix_web::{web, HttpResponse, Responder};
+use mysql::Pool;
+
+use crate::common::errors::{AppError, ProblemDetails};
+use crate::common::telemetry;
+use crate::model::requests::VoidReportQuery;
+use crate::model::resources::{TableItem, VoidReason, VoidedItemResource};
+use crate::model::responses::VoidReportResponse;
+use crate::model::time::RestaurantTimezone;
+use crate::persist::fetch_table_items::get_voided_items;
+
+#[utoipa::path(
+    get,
+    path = "/reports/voided-items",
+    context_path = "/v1",
+    tag = "reports",
+    params(VoidReportQuery),
+    responses(
+        (status = 200, body = VoidReportResponse),
+        (status = 400, body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub(crate) async fn get_voided_items_report(
+    query: web::Query<VoidReportQuery>,
+    data: web::Data<Pool>,
+    timezone: web::Data<RestaurantTimezone>,
+    request_id: RequestId,
+) -> Result<impl Responder, AppError> {
+    let report_query = query.into_inner();
+    let timezone = **timezone;
+
+    let items = telemetry::block(move ||
+        get_voided_items(&data, request_id, timezone, report_query)
+    ).await??;
+
+    Ok(HttpResponse::Ok().json(VoidReportResponse {
+        total: items.len(),
+        by_reason: count_by_reason(&items),
+        items: items.into_iter().map(|item| VoidedItemResource::new(&timezone, item)).collect(),
+    }))
+}
+
+pub(crate) fn count_by_reason(items: &[TableItem]) -> HashMap<VoidReason, usize> {
+    let mut by_reason = HashMap::new();
+    for reason in items.iter().filter_map(|item| item.void_reason) {
+        *by_reason.entry(reason).or_insert(0) += 1;
+    }
+    by_reason
+}
@@ -1,125 +1,428 @@
 use actix_request_identifier::RequestId;
-use actix_web::{delete, get, post, web, HttpResponse, Responder};
+use actix_web::http::header::{ETag, LINK};
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
 use mysql::Pool;
 
-use crate::common::errors::PersistenceError;
-use crate::model::requests::{AddItemsToTableRequest};
-use crate::model::resources::{TableItemResource, TableResource};
-use crate::persist::fetch_table_items::get_table_items;
-use crate::persist::persist_table_items::add_items_to_table;
-use crate::persist::remove_table_items::remove_table_item;
+use crate::common::actor::Actor;
+use crate::common::errors::{AppError, FieldError, ProblemDetails};
+use crate::common::etag::{is_not_modified, item_etag, item_precondition, table_etag};
+use crate::common::representation::Representation;
+use crate::common::routes::{Links, Route};
+use crate::common::telemetry;
+use crate::model::pagination::{Cursor, PageRequest};
+use crate::model::requests::{AddItemsToTableRequest, BulkUpdateStatusRequest, BulkVoidItemsRequest, TableItemsQuery, VoidItemQuery};
+use crate::model::resources::{TableItem, TableItemResource, TableResource};
+use crate::model::responses::{AddItemsResponse, BulkItemOutcome, BulkOperationResponse, RemoveTableItemResponse};
+use crate::model::time::RestaurantTimezone;
+use crate::model::validation::{validate_idempotency_key, Validate};
+use crate::persist::bulk_table_items::{update_table_items_status, void_table_items};
+use crate::persist::fetch_table_items::{get_table_contents, get_table_items, get_table_items_page, ItemsFilter};
+use crate::persist::idempotency_keys::{IdempotencyConfig, IdempotencyKey};
+use crate::persist::persist_table_items::{add_items_to_table, AddItemsOutcome};
+use crate::persist::void_table_items::{restore_table_item, void_table_item};
 
-#[post("/tables/{table_number}/items")]
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+pub(crate) const IDEMPOTENT_REPLAYED_HEADER: &str = "Idempotent-Replayed";
+
+#[utoipa::path(
+    post,
+    path = "/tables/{table_number}/items",
+    context_path = "/v1",
+    tag = "items",
+    params(
+        ("table_number" = u32, Path),
+        ("Idempotency-Key" = Option<String>, Header, description = "Replays the stored response when the same request is retried"),
+        ("X-Actor" = Option<String>, Header, description = "Who performs the change, recorded in the audit log"),
+    ),
+    request_body = AddItemsToTableRequest,
+    responses(
+        (status = 201, description = "Items added", body = AddItemsResponse),
+        (status = 400, body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "Idempotency-Key reused with a different request", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
 pub(crate) async fn add_items(
+    req: HttpRequest,
     path: web::Path<u32>,
     web::Json(request): web::Json<AddItemsToTableRequest>,
     data: web::Data<Pool>,
+    idempotency_config: web::Data<IdempotencyConfig>,
     request_id: RequestId,
-) -> actix_web::Result<impl Responder> {
+    actor: Actor,
+) -> Result<impl Responder, AppError> {
     let table_number = path.into_inner();
+    request.validate()?;
+    let idempotency_key = extract_idempotency_key(&req, &idempotency_config)?;
 
-    let response = web::block(move ||
+    let outcome = telemetry::block(move ||
         add_items_to_table(
-            &data, request_id,
+            &data, request_id, actor,
             table_number,
             request.items_names,
+            idempotency_key,
         )).await??;
 
-    Ok(HttpResponse::Created().json(response))
+    match outcome {
+        AddItemsOutcome::Created(response) => Ok(HttpResponse::Created().json(response)),
+        AddItemsOutcome::Replayed(response) => Ok(HttpResponse::Created()
+            .insert_header((IDEMPOTENT_REPLAYED_HEADER, "true"))
+            .json(response)),
+    }
+}
+
+pub(crate) fn extract_idempotency_key(
+    req: &HttpRequest,
+    idempotency_config: &IdempotencyConfig,
+) -> Result<Option<IdempotencyKey>, AppError> {
+    let Some(value) = req.headers().get(IDEMPOTENCY_KEY_HEADER) else { return Ok(None) };
+    let key = validate_idempotency_key(IDEMPOTENCY_KEY_HEADER, value.as_bytes())?;
+    Ok(Some(IdempotencyKey { key, ttl_seconds: idempotency_config.ttl_seconds }))
 }
 
-#[get("/tables/{table_number}/items")]
+#[utoipa::path(
+    get,
+    path = "/tables/{table_number}/items",
+    context_path = "/v1",
+    tag = "items",
+    params(
+        ("table_number" = u32, Path),
+        ("name" = Option<Vec<String>>, Query, explode, description = "Exact item name, repeatable"),
+        ("id" = Option<Vec<u32>>, Query, explode, description = "Item id, repeatable"),
+        TableItemsQuery,
+    ),
+    responses(
+        (status = 200, description = "One page of the live items of the table", body = Vec<TableItemResource>,
+            headers(("Link" = String, description = "`next` and `prev` pages, when there are some"))),
+        (status = 400, body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
 pub(crate) async fn get_all_items(
+    req: HttpRequest,
     path: web::Path<u32>,
     data: web::Data<Pool>,
     request_id: RequestId,
-) -> actix_web::Result<impl Responder> {
+    timezone: web::Data<RestaurantTimezone>,
+    links: Links,
+) -> Result<impl Responder, AppError> {
     let table_number = path.into_inner();
-
-    let items = web::block(move ||
-        get_table_items(&data, request_id, table_number, None, None)
-    ).await??;
+    let ListedItems { items, next_query, prev_query, .. } =
+        list_items(&req, &data, &timezone, request_id, table_number).await?;
 
     let mut items_resources: Vec<TableItemResource> = vec![];
     for item in items {
-        let item_resource = TableItemResource::new(
-            item.item_id,
-            item.table_number,
-            item.item_name,
-            item.ordered_on,
-            item.prepare_minutes,
-        );
+        let item_resource = TableItemResource::new(&links, &timezone, item);
         items_resources.push(item_resource);
     }
 
-    Ok(HttpResponse::Ok().json(items_resources))
+    let mut builder = HttpResponse::Ok();
+    if let Some(link) = page_link_header(&links, table_number, next_query, prev_query) {
+        builder.insert_header((LINK, link));
+    }
+    Ok(builder.json(items_resources))
 }
 
-#[get("/tables/{table_number}")]
+/// RFC 8288 `Link` header with the `next` and `prev` pages, `None` on a single page listing.
+fn page_link_header(links: &Links, table_number: u32,
+                    next_query: Option<TableItemsQuery>, prev_query: Option<TableItemsQuery>) -> Option<String> {
+    let href = links.href(Route::TableItems, &[table_number]);
+    let page_links: Vec<String> = [("next", next_query), ("prev", prev_query)].into_iter()
+        .filter_map(|(rel, query)| Some(format!("<{href}?{}>; rel=\"{rel}\"", query?.to_query_string())))
+        .collect();
+    (!page_links.is_empty()).then(|| page_links.join(", "))
+}
+
+/// Items of one listing page, with the queries of the current, next and previous pages.
+pub(crate) struct ListedItems {
+    pub items: Vec<TableItem>,
+    pub query: TableItemsQuery,
+    pub next_query: Option<TableItemsQuery>,
+    pub prev_query: Option<TableItemsQuery>,
+}
+
+pub(crate) async fn list_items(
+    req: &HttpRequest,
+    data: &web::Data<Pool>,
+    timezone: &RestaurantTimezone,
+    request_id: RequestId,
+    table_number: u32,
+) -> Result<ListedItems, AppError> {
+    let items_query = TableItemsQuery::from_query_string(req.query_string())?;
+    let page = PageRequest::from_query(&items_query)?;
+    let filter = ItemsFilter {
+        items_ids: Some(items_query.id.clone()).filter(|ids| !ids.is_empty()),
+        items_names: Some(items_query.name.clone()).filter(|names| !names.is_empty()),
+        name_contains: items_query.name_contains.clone(),
+        status: items_query.status,
+        ordered_from: items_query.ordered_from.as_ref().map(|from| timezone.resolve(from)),
+        ordered_to: items_query.ordered_to.as_ref().map(|to| timezone.resolve(to)),
+        overdue: items_query.overdue,
+    };
+    if let (Some(from), Some(to)) = (filter.ordered_from, filter.ordered_to) {
+        if from > to {
+            return Err(AppError::Validation(vec![FieldError::new("ordered_from", "must not be after ordered_to")]));
+        }
+    }
+
+    let sort_field = page.sort.field;
+    let backward = page.is_backward();
+    let data = data.clone();
+    let items_page = telemetry::block(move ||
+        get_table_items_page(&data, request_id, table_number, filter, page)
+    ).await??;
+
+    let items = items_page.items;
+    let has_next = if backward { true } else { items_page.has_more };
+    let has_prev = if backward { items_page.has_more } else { items_query.after.is_some() };
+    let next_query = items.last().filter(|_| has_next).map(|last| TableItemsQuery {
+        after: Some(Cursor::of(last, sort_field).encode()),
+        before: None,
+        ..items_query.clone()
+    });
+    let prev_query = items.first().filter(|_| has_prev).map(|first| TableItemsQuery {
+        after: None,
+        before: Some(Cursor::of(first, sort_field).encode()),
+        ..items_query.clone()
+    });
+
+    Ok(ListedItems { items, query: items_query, next_query, prev_query })
+}
+
+#[utoipa::path(
+    get,
+    path = "/tables/{table_number}",
+    context_path = "/v1",
+    tag = "tables",
+    params(("table_number" = u32, Path)),
+    responses(
+        (status = 200, body = TableResource, headers(("ETag" = String))),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag"),
+        (status = 404, body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
 pub(crate) async fn get_table(
+    req: HttpRequest,
     path: web::Path<u32>,
     data: web::Data<Pool>,
     request_id: RequestId,
-) -> actix_web::Result<impl Responder> {
+    timezone: web::Data<RestaurantTimezone>,
+    links: Links,
+    representation: Representation,
+) -> Result<impl Responder, AppError> {
     let table_number = path.into_inner();
 
-    let items = web::block(move ||
-        get_table_items(&data, request_id, table_number, None, None)
+    let items = telemetry::block(move ||
+        get_table_contents(&data, request_id, table_number)
     ).await??;
 
+    let etag = table_etag(table_number, &items, representation);
+    if is_not_modified(&req, &etag) {
+        return Ok(HttpResponse::NotModified().insert_header(ETag(etag)).finish());
+    }
+
     let mut items_resources: Vec<TableItemResource> = vec![];
     for item in items {
-        let item_resource = TableItemResource::new(
-            item.item_id,
-            item.table_number,
-            item.item_name,
-            item.ordered_on,
-            item.prepare_minutes,
-        );
+        let item_resource = TableItemResource::new(&links, &timezone, item);
         items_resources.push(item_resource);
     }
 
-    let table = TableResource::new(table_number, items_resources);
-    Ok(HttpResponse::Ok().json(table))
+    let table = TableResource::new(&links, table_number, items_resources);
+    Ok(representation.respond(HttpResponse::Ok().insert_header(ETag(etag)), &table))
 }
 
-#[get("/tables/{table_number}/items/{item_id}")]
+#[utoipa::path(
+    get,
+    path = "/tables/{table_number}/items/{item_id}",
+    context_path = "/v1",
+    tag = "items",
+    params(("table_number" = u32, Path), ("item_id" = u32, Path)),
+    responses(
+        (status = 200, body = TableItemResource, headers(("ETag" = String))),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag"),
+        (status = 404, body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
 pub(crate) async fn get_item(
+    req: HttpRequest,
     path: web::Path<(u32, u32)>,
     data: web::Data<Pool>,
     request_id: RequestId,
-) -> actix_web::Result<impl Responder> {
+    timezone: web::Data<RestaurantTimezone>,
+    links: Links,
+    representation: Representation,
+) -> Result<impl Responder, AppError> {
     let (table_number, item_id) = path.into_inner();
     let items_ids = vec![item_id];
 
-    let response = web::block(move ||
+    let response = telemetry::block(move ||
         get_table_items(&data, request_id, table_number, items_ids.into(), None)
     ).await??;
 
-    if let Some(item) = response.get(0) {
-        let item_resource = TableItemResource::new(
-            item.item_id,
-            item.table_number,
-            item.item_name.clone(),
-            item.ordered_on.clone(),
-            item.prepare_minutes,
-        );
-        Ok(HttpResponse::Ok().json(item_resource))
+    if let Some(item) = response.into_iter().next() {
+        let etag = item_etag(&item, representation);
+        if is_not_modified(&req, &etag) {
+            return Ok(HttpResponse::NotModified().insert_header(ETag(etag)).finish());
+        }
+        let item_resource = TableItemResource::new(&links, &timezone, item);
+        Ok(representation.respond(HttpResponse::Ok().insert_header(ETag(etag)), &item_resource))
     } else {
-        Err(PersistenceError::ResourceNotFound.into())
+        Err(AppError::NotFound(format!("Item {item_id} not found on table {table_number}")))
     }
 }
 
-#[delete("/tables/{table_number}/items/{item_id}")]
+#[utoipa::path(
+    delete,
+    path = "/tables/{table_number}/items/{item_id}",
+    context_path = "/v1",
+    tag = "items",
+    params(
+        ("table_number" = u32, Path),
+        ("item_id" = u32, Path),
+        VoidItemQuery,
+        ("If-Match" = String, Header, description = "ETag of the item, or `*`"),
+        ("X-Actor" = Option<String>, Header),
+    ),
+    responses(
+        (status = 200, description = "Item voided", body = RemoveTableItemResponse, headers(("ETag" = String))),
+        (status = 404, body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "Item already voided", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 412, body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 428, body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
 pub(crate) async fn remove_item(
+    req: HttpRequest,
+    path: web::Path<(u32, u32)>,
+    query: web::Query<VoidItemQuery>,
+    data: web::Data<Pool>,
+    request_id: RequestId,
+    actor: Actor,
+) -> Result<impl Responder, AppError> {
+    let (table_number, item_id) = path.into_inner();
+    let reason = query.into_inner().reason;
+    let precondition = item_precondition(&req, item_id)?;
+
+    let (response, voided) = telemetry::block(move ||
+        void_table_item(&data, request_id, actor, table_number, item_id, reason, precondition)
+    ).await??;
+    Ok(HttpResponse::Ok().insert_header(ETag(item_etag(&voided, Representation::Json))).json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/tables/{table_number}/items/{item_id}/restore",
+    context_path = "/v1",
+    tag = "items",
+    params(
+        ("table_number" = u32, Path),
+        ("item_id" = u32, Path),
+        ("If-Match" = String, Header, description = "ETag of the item, or `*`"),
+        ("X-Actor" = Option<String>, Header),
+    ),
+    responses(
+        (status = 200, description = "Item restored", body = TableItemResource, headers(("ETag" = String))),
+        (status = 404, body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "Item is not voided", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 412, body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 428, body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn restore_item(
+    req: HttpRequest,
     path: web::Path<(u32, u32)>,
     data: web::Data<Pool>,
     request_id: RequestId,
-) -> actix_web::Result<impl Responder> {
+    actor: Actor,
+    timezone: web::Data<RestaurantTimezone>,
+    links: Links,
+    representation: Representation,
+) -> Result<impl Responder, AppError> {
     let (table_number, item_id) = path.into_inner();
+    let precondition = item_precondition(&req, item_id)?;
+
+    let item = telemetry::block(move ||
+        restore_table_item(&data, request_id, actor, table_number, item_id, precondition)
+    ).await??;
+    let etag = item_etag(&item, representation);
+
+    let item_resource = TableItemResource::new(&links, &timezone, item);
+    Ok(representation.respond(HttpResponse::Ok().insert_header(ETag(etag)), &item_resource))
+}
+
+#[utoipa::path(
+    post,
+    path = "/tables/{table_number}/items/bulk-void",
+    context_path = "/v1",
+    tag = "items",
+    params(("table_number" = u32, Path), ("X-Actor" = Option<String>, Header)),
+    request_body = BulkVoidItemsRequest,
+    responses(
+        (status = 200, description = "Outcome of every item", body = BulkOperationResponse),
+        (status = 400, body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "All-or-nothing batch rolled back", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub(crate) async fn bulk_void_items(
+    path: web::Path<u32>,
+    web::Json(request): web::Json<BulkVoidItemsRequest>,
+    data: web::Data<Pool>,
+    request_id: RequestId,
+    actor: Actor,
+) -> Result<impl Responder, AppError> {
+    let table_number = path.into_inner();
+    request.validate()?;
 
-    let response = web::block(move ||
-        remove_table_item(&data, request_id, table_number, item_id)
+    let response = telemetry::block(move ||
+        void_table_items(&data, request_id, actor, table_number, request.items, request.reason, request.mode)
     ).await??;
-    Ok(HttpResponse::Ok().json(response))
-}
\ No newline at end of file
+    bulk_response(response)
+}
+
+#[utoipa::path(
+    post,
+    path = "/tables/{table_number}/items/bulk-status",
+    context_path = "/v1",
+    tag = "items",
+    params(("table_number" = u32, Path), ("X-Actor" = Option<String>, Header)),
+    request_body = BulkUpdateStatusRequest,
+    responses(
+        (status = 200, description = "Outcome of every item", body = BulkOperationResponse),
+        (status = 400, body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "All-or-nothing batch rolled back", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub(crate) async fn bulk_update_status(
+    path: web::Path<u32>,
+    web::Json(request): web::Json<BulkUpdateStatusRequest>,
+    data: web::Data<Pool>,
+    request_id: RequestId,
+    actor: Actor,
+) -> Result<impl Responder, AppError> {
+    let table_number = path.into_inner();
+    request.validate()?;
+
+    let response = telemetry::block(move ||
+        update_table_items_status(&data, request_id, actor, table_number, request.items, request.status, request.mode)
+    ).await??;
+    bulk_response(response)
+}
+
+/// A rolled back batch is a conflict listing the items that caused it; otherwise every outcome is returned.
+fn bulk_response(response: BulkOperationResponse) -> Result<HttpResponse, AppError> {
+    if response.committed {
+        return Ok(HttpResponse::Ok().json(response));
+    }
+    let errors = response.results.iter().enumerate()
+        .filter(|(_, result)| result.outcome != BulkItemOutcome::Applied)
+        .map(|(index, result)| FieldError::new(
+            format!("items[{index}]"),
+            format!("item {} {}", result.item_id, match result.outcome {
+                BulkItemOutcome::NotFound => "was not found",
+                BulkItemOutcome::Conflict => "is not in a state allowing this operation",
+                BulkItemOutcome::VersionMismatch => "was modified since it was fetched",
+                BulkItemOutcome::Applied => "was applied",
+            }),
+        ))
+        .collect();
+    Err(AppError::BulkRolledBack(errors))
+}
@@ -0,0 +1,324 @@
+use actix_request_identifier::RequestId;
+use actix_web::http::header::ETag;
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use mysql::Pool;
+
+use crate::api::report_api::count_by_reason;
+use crate::api::restaurant_api::{extract_idempotency_key, list_items, ListedItems, IDEMPOTENT_REPLAYED_HEADER};
+use crate::common::actor::Actor;
+use crate::common::errors::{AppError, ProblemDetails};
+use crate::common::etag::{is_not_modified, item_etag, item_precondition, table_etag};
+use crate::common::representation::Representation;
+use crate::common::telemetry;
+use crate::common::routes::Links;
+use crate::model::requests::{AuditQuery, TableItemsQuery, VoidItemQuery, VoidReportQuery};
+use crate::model::resources::AuditEntry;
+use crate::model::v2::{AddItemsRequest, AddItemsResponse, AddedItem, ItemResource, ItemsPageResource, TableResource, VoidReportResponse};
+use crate::model::time::RestaurantTimezone;
+use crate::model::validation::Validate;
+use crate::persist::audit_log::get_audit_entries;
+use crate::persist::fetch_table_items::{get_table_contents, get_table_items, get_voided_items};
+use crate::persist::idempotency_keys::IdempotencyConfig;
+use crate::persist::persist_table_items::{add_items_to_table, AddItemsOutcome};
+use crate::persist::void_table_items::{restore_table_item, void_table_item};
+
+#[utoipa::path(
+    post,
+    path = "/tables/{table_number}/items",
+    context_path = "/v2",
+    tag = "v2",
+    params(
+        ("table_number" = u32, Path),
+        ("Idempotency-Key" = Option<String>, Header, description = "Replays the stored response when the same request is retried"),
+        ("X-Actor" = Option<String>, Header, description = "Who performs the change, recorded in the audit log"),
+    ),
+    request_body = AddItemsRequest,
+    responses(
+        (status = 201, description = "Items added", body = AddItemsResponse),
+        (status = 400, body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "Idempotency-Key reused with a different request", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub(crate) async fn add_items(
+    req: HttpRequest,
+    path: web::Path<u32>,
+    web::Json(request): web::Json<AddItemsRequest>,
+    data: web::Data<Pool>,
+    idempotency_config: web::Data<IdempotencyConfig>,
+    request_id: RequestId,
+    actor: Actor,
+) -> Result<impl Responder, AppError> {
+    let table_number = path.into_inner();
+    request.validate()?;
+    let idempotency_key = extract_idempotency_key(&req, &idempotency_config)?;
+    let items_names: Vec<String> = request.items.into_iter().map(|item| item.item_name).collect();
+
+    let names = items_names.clone();
+    let outcome = telemetry::block(move ||
+        add_items_to_table(&data, request_id, actor, table_number, names, idempotency_key)
+    ).await??;
+
+    // A replay matched the stored request fingerprint, so the names line up with the stored ids.
+    let (response, replayed) = match outcome {
+        AddItemsOutcome::Created(response) => (response, false),
+        AddItemsOutcome::Replayed(response) => (response, true),
+    };
+    let items = response.items_ids.into_iter().zip(items_names)
+        .map(|(item_id, item_name)| AddedItem { item_id, item_name })
+        .collect();
+
+    let mut builder = HttpResponse::Created();
+    if replayed {
+        builder.insert_header((IDEMPOTENT_REPLAYED_HEADER, "true"));
+    }
+    Ok(builder.json(AddItemsResponse { items }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/tables/{table_number}/items",
+    context_path = "/v2",
+    tag = "v2",
+    params(
+        ("table_number" = u32, Path),
+        ("name" = Option<Vec<String>>, Query, explode, description = "Exact item name, repeatable"),
+        ("id" = Option<Vec<u32>>, Query, explode, description = "Item id, repeatable"),
+        TableItemsQuery,
+    ),
+    responses(
+        (status = 200, description = "One page of the live items of the table", body = ItemsPageResource),
+        (status = 400, body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub(crate) async fn get_all_items(
+    req: HttpRequest,
+    path: web::Path<u32>,
+    data: web::Data<Pool>,
+    request_id: RequestId,
+    timezone: web::Data<RestaurantTimezone>,
+    links: Links,
+    representation: Representation,
+) -> Result<impl Responder, AppError> {
+    let table_number = path.into_inner();
+    let ListedItems { items, query, next_query, prev_query } =
+        list_items(&req, &data, &timezone, request_id, table_number).await?;
+
+    let page_resource = ItemsPageResource::new(
+        &links,
+        table_number,
+        items.into_iter().map(|item| ItemResource::new(&links, &timezone, item)).collect(),
+        &query.to_query_string(),
+        next_query.as_ref().map(TableItemsQuery::to_query_string),
+        prev_query.as_ref().map(TableItemsQuery::to_query_string),
+    );
+    Ok(representation.respond(&mut HttpResponse::Ok(), &page_resource))
+}
+
+#[utoipa::path(
+    get,
+    path = "/tables/{table_number}",
+    context_path = "/v2",
+    tag = "v2",
+    params(("table_number" = u32, Path)),
+    responses(
+        (status = 200, body = TableResource, headers(("ETag" = String))),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag"),
+        (status = 404, body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub(crate) async fn get_table(
+    req: HttpRequest,
+    path: web::Path<u32>,
+    data: web::Data<Pool>,
+    request_id: RequestId,
+    timezone: web::Data<RestaurantTimezone>,
+    links: Links,
+    representation: Representation,
+) -> Result<impl Responder, AppError> {
+    let table_number = path.into_inner();
+
+    let items = telemetry::block(move ||
+        get_table_contents(&data, request_id, table_number)
+    ).await??;
+
+    let etag = table_etag(table_number, &items, representation);
+    if is_not_modified(&req, &etag) {
+        return Ok(HttpResponse::NotModified().insert_header(ETag(etag)).finish());
+    }
+
+    let items = items.into_iter().map(|item| ItemResource::new(&links, &timezone, item)).collect();
+    let table = TableResource::new(&links, table_number, items);
+    Ok(representation.respond(HttpResponse::Ok().insert_header(ETag(etag)), &table))
+}
+
+#[utoipa::path(
+    get,
+    path = "/tables/{table_number}/items/{item_id}",
+    context_path = "/v2",
+    tag = "v2",
+    params(("table_number" = u32, Path), ("item_id" = u32, Path)),
+    responses(
+        (status = 200, body = ItemResource, headers(("ETag" = String))),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag"),
+        (status = 404, body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub(crate) async fn get_item(
+    req: HttpRequest,
+    path: web::Path<(u32, u32)>,
+    data: web::Data<Pool>,
+    request_id: RequestId,
+    timezone: web::Data<RestaurantTimezone>,
+    links: Links,
+    representation: Representation,
+) -> Result<impl Responder, AppError> {
+    let (table_number, item_id) = path.into_inner();
+    let items_ids = vec![item_id];
+
+    let item = telemetry::block(move ||
+        get_table_items(&data, request_id, table_number, items_ids.into(), None)
+    ).await??.into_iter().next()
+        .ok_or_else(|| AppError::NotFound(format!("Item {item_id} not found on table {table_number}")))?;
+
+    let etag = item_etag(&item, representation);
+    if is_not_modified(&req, &etag) {
+        return Ok(HttpResponse::NotModified().insert_header(ETag(etag)).finish());
+    }
+    Ok(representation.respond(HttpResponse::Ok().insert_header(ETag(etag)), &ItemResource::new(&links, &timezone, item)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/tables/{table_number}/items/{item_id}",
+    context_path = "/v2",
+    tag = "v2",
+    params(
+        ("table_number" = u32, Path),
+        ("item_id" = u32, Path),
+        VoidItemQuery,
+        ("If-Match" = String, Header, description = "ETag of the item, or `*`"),
+        ("X-Actor" = Option<String>, Header),
+    ),
+    responses(
+        (status = 204, description = "Item voided", headers(("ETag" = String))),
+        (status = 404, body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "Item already voided", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 412, body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 428, body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub(crate) async fn remove_item(
+    req: HttpRequest,
+    path: web::Path<(u32, u32)>,
+    query: web::Query<VoidItemQuery>,
+    data: web::Data<Pool>,
+    request_id: RequestId,
+    actor: Actor,
+) -> Result<impl Responder, AppError> {
+    let (table_number, item_id) = path.into_inner();
+    let reason = query.into_inner().reason;
+    let precondition = item_precondition(&req, item_id)?;
+
+    let (_, voided) = telemetry::block(move ||
+        void_table_item(&data, request_id, actor, table_number, item_id, reason, precondition)
+    ).await??;
+    Ok(HttpResponse::NoContent().insert_header(ETag(item_etag(&voided, Representation::Json))).finish())
+}
+
+#[utoipa::path(
+    post,
+    path = "/tables/{table_number}/items/{item_id}/restore",
+    context_path = "/v2",
+    tag = "v2",
+    params(
+        ("table_number" = u32, Path),
+        ("item_id" = u32, Path),
+        ("If-Match" = String, Header, description = "ETag of the item, or `*`"),
+        ("X-Actor" = Option<String>, Header),
+    ),
+    responses(
+        (status = 200, description = "Item restored", body = ItemResource, headers(("ETag" = String))),
+        (status = 404, body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 409, description = "Item is not voided", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 412, body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 428, body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn restore_item(
+    req: HttpRequest,
+    path: web::Path<(u32, u32)>,
+    data: web::Data<Pool>,
+    request_id: RequestId,
+    actor: Actor,
+    timezone: web::Data<RestaurantTimezone>,
+    links: Links,
+    representation: Representation,
+) -> Result<impl Responder, AppError> {
+    let (table_number, item_id) = path.into_inner();
+    let precondition = item_precondition(&req, item_id)?;
+
+    let item = telemetry::block(move ||
+        restore_table_item(&data, request_id, actor, table_number, item_id, precondition)
+    ).await??;
+    let etag = item_etag(&item, representation);
+    Ok(representation.respond(HttpResponse::Ok().insert_header(ETag(etag)), &ItemResource::new(&links, &timezone, item)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/audit",
+    context_path = "/v2",
+    tag = "v2",
+    params(AuditQuery),
+    responses(
+        (status = 200, description = "Most recent entries first, timestamps in UTC", body = Vec<AuditEntry>),
+        (status = 400, body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub(crate) async fn get_audit_log(
+    query: web::Query<AuditQuery>,
+    data: web::Data<Pool>,
+    timezone: web::Data<RestaurantTimezone>,
+    request_id: RequestId,
+) -> Result<impl Responder, AppError> {
+    let audit_query = query.into_inner();
+    let timezone = **timezone;
+
+    let entries = telemetry::block(move ||
+        get_audit_entries(&data, request_id, timezone, audit_query)
+    ).await??;
+
+    Ok(HttpResponse::Ok().json(entries))
+}
+
+#[utoipa::path(
+    get,
+    path = "/reports/voided-items",
+    context_path = "/v2",
+    tag = "v2",
+    params(VoidReportQuery),
+    responses(
+        (status = 200, body = VoidReportResponse),
+        (status = 400, body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub(crate) async fn get_voided_items_report(
+    query: web::Query<VoidReportQuery>,
+    data: web::Data<Pool>,
+    timezone: web::Data<RestaurantTimezone>,
+    request_id: RequestId,
+) -> Result<impl Responder, AppError> {
+    let report_query = query.into_inner();
+    let timezone = **timezone;
+
+    let items = telemetry::block(move ||
+        get_voided_items(&data, request_id, timezone, report_query)
+    ).await??;
+
+    Ok(HttpResponse::Ok().json(VoidReportResponse {
+        total: items.len(),
+        by_reason: count_by_reason(&items),
+        items,
+    }))
+}
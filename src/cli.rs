@@ -0,0 +1,248 @@
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use std::str::FromStr;
+
+use chrono::{NaiveDate, NaiveTime, SecondsFormat};
+use clap::{Parser, Subcommand, ValueEnum};
+use mysql::Pool;
+
+use crate::common::errors::AppError;
+use crate::model::resources::{Menu, TableItem};
+use crate::model::time::{RestaurantTimezone, TimeBound, V1_TIMESTAMP_FORMAT};
+use crate::model::validation::Validate;
+use crate::persist::maintenance::{export_items, list_tables, purge_items};
+use crate::persist::menu_items::upsert_menu_items;
+use crate::persist::migrations::{migrate_down, migrate_up, migration_status};
+
+/// Restaurant staff API. Serves it unless a subcommand is given.
+#[derive(Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// TOML file with settings; environment variables take precedence over it.
+    #[arg(long, global = true, env = "CONFIG_FILE")]
+    pub config: Option<PathBuf>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Serve the HTTP API (the default).
+    Serve,
+    /// Print the effective configuration with secrets redacted.
+    Config,
+    /// Manage the database schema.
+    Migrate {
+        #[command(subcommand)]
+        action: MigrateAction,
+    },
+    /// Add dishes and their preparation times from a TOML file.
+    ///
+    /// The file lists `[[items]]` with `item_name` and `prepare_minutes`, see `menu.example.toml`.
+    /// Dishes already on the menu are updated.
+    SeedMenu {
+        file: PathBuf,
+    },
+    /// Inspect tables.
+    Tables {
+        #[command(subcommand)]
+        action: TablesAction,
+    },
+    /// Maintain items.
+    Items {
+        #[command(subcommand)]
+        action: ItemsAction,
+    },
+    /// Write all items, voided ones included, to stdout.
+    Export {
+        /// Only the items of this table.
+        #[arg(long)]
+        table: Option<u32>,
+        #[arg(long, value_enum, default_value_t = ExportFormat::Jsonl)]
+        format: ExportFormat,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum MigrateAction {
+    /// Apply all pending migrations.
+    Up,
+    /// Revert the latest applied migration.
+    Down,
+    /// List the migrations and when they were applied.
+    Status,
+}
+
+#[derive(Subcommand)]
+pub enum TablesAction {
+    /// List the tables with items that are not voided.
+    List,
+}
+
+#[derive(Subcommand)]
+pub enum ItemsAction {
+    /// Delete the items ordered before a time; the audit log keeps their history.
+    Purge {
+        /// RFC 3339, or the restaurant's local time when given without an offset. A date alone
+        /// (`2024-01-01`) means the restaurant's midnight starting it.
+        #[arg(long, value_parser = parse_purge_cutoff)]
+        before: TimeBound,
+        /// Only count the items that would be deleted.
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+fn parse_purge_cutoff(value: &str) -> Result<TimeBound, String> {
+    value.parse().or_else(|e| NaiveDate::from_str(value)
+        .map(|date| TimeBound::Local(date.and_time(NaiveTime::MIN)))
+        .map_err(|_| format!("{e}, or YYYY-MM-DD")))
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    /// One JSON item per line.
+    Jsonl,
+    /// Comma separated values with a header row.
+    Csv,
+}
+
+/// Runs an administration command; `serve` and `config` are handled by `main`.
+pub fn run(command: Command, pool: &Pool, timezone: RestaurantTimezone) -> Result<(), String> {
+    match command {
+        Command::Serve | Command::Config => Ok(()),
+        Command::Migrate { action } => run_migrate(pool, action, timezone),
+        Command::SeedMenu { file } => seed_menu(pool, &file),
+        Command::Tables { action: TablesAction::List } => print_tables(pool, timezone),
+        Command::Items { action: ItemsAction::Purge { before, dry_run } } => {
+            let purged = purge_items(pool, timezone.resolve(&before), dry_run).map_err(|e| e.to_string())?;
+            if dry_run {
+                println!("Would purge {purged} items ordered before {before}");
+            } else {
+                println!("Purged {purged} items ordered before {before}");
+            }
+            Ok(())
+        }
+        Command::Export { table, format } => export(pool, table, format),
+    }
+}
+
+fn run_migrate(pool: &Pool, action: MigrateAction, timezone: RestaurantTimezone) -> Result<(), String> {
+    match action {
+        MigrateAction::Up => {
+            let migrated = migrate_up(pool, timezone).map_err(|e| e.to_string())?;
+            if migrated.is_empty() {
+                println!("Schema is up to date");
+            }
+            for migration in migrated {
+                println!("Applied {}", migration.name);
+            }
+        }
+        MigrateAction::Down => match migrate_down(pool, timezone).map_err(|e| e.to_string())? {
+            Some(migration) => println!("Reverted {}", migration.name),
+            None => println!("No migration to revert"),
+        },
+        MigrateAction::Status => {
+            for status in migration_status(pool).map_err(|e| e.to_string())? {
+                match status.applied_on {
+                    Some(applied_on) => println!("{}  applied {}", status.migration.name, applied_on.to_rfc3339()),
+                    None => println!("{}  pending", status.migration.name),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn seed_menu(pool: &Pool, file: &Path) -> Result<(), String> {
+    let content = std::fs::read_to_string(file).map_err(|e| format!("{}: {e}", file.display()))?;
+    let menu: Menu = toml::from_str(&content).map_err(|e| format!("{}: {}", file.display(), e.message()))?;
+    if let Err(AppError::Validation(errors)) = menu.validate() {
+        let problems: Vec<String> = errors.iter().map(|error| format!("{}: {}", error.field, error.message)).collect();
+        return Err(format!("{}: {}", file.display(), problems.join("; ")));
+    }
+
+    upsert_menu_items(pool, &menu.items).map_err(|e| e.to_string())?;
+    println!("Seeded {} menu items", menu.items.len());
+    Ok(())
+}
+
+fn print_tables(pool: &Pool, timezone: RestaurantTimezone) -> Result<(), String> {
+    let tables = list_tables(pool).map_err(|e| e.to_string())?;
+    println!("{:>5}  {:>5}  {:>7}  first ordered", "table", "items", "pending");
+    for table in tables {
+        println!(
+            "{:>5}  {:>5}  {:>7}  {}",
+            table.table_number,
+            table.items,
+            table.pending,
+            timezone.local(table.first_ordered_on).format(V1_TIMESTAMP_FORMAT),
+        );
+    }
+    Ok(())
+}
+
+fn export(pool: &Pool, table_number: Option<u32>, format: ExportFormat) -> Result<(), String> {
+    let items = export_items(pool, table_number).map_err(|e| e.to_string())?;
+    let mut out = BufWriter::new(std::io::stdout().lock());
+    let written = match format {
+        ExportFormat::Jsonl => items.iter().try_for_each(|item| {
+            serde_json::to_writer(&mut out, item).map_err(std::io::Error::from)?;
+            writeln!(out)
+        }),
+        ExportFormat::Csv => writeln!(out, "item_id,table_number,item_name,ordered_on,prepare_minutes,status,version,voided_on,void_reason")
+            .and_then(|_| items.iter().try_for_each(|item| writeln!(out, "{}", csv_row(item)))),
+    };
+    written.and_then(|_| out.flush()).map_err(|e| e.to_string())
+}
+
+fn csv_row(item: &TableItem) -> String {
+    [
+        item.item_id.to_string(),
+        item.table_number.to_string(),
+        format!("\"{}\"", item.item_name.replace('"', "\"\"")),
+        item.ordered_on.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        item.prepare_minutes.to_string(),
+        item.status.as_str().to_string(),
+        item.version.to_string(),
+        item.voided_on.map(|voided_on| voided_on.to_rfc3339_opts(SecondsFormat::AutoSi, true)).unwrap_or_default(),
+        item.void_reason.map(|void_reason| void_reason.as_str().to_string()).unwrap_or_default(),
+    ].join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use clap::Parser;
+
+    use super::{Cli, Command, ItemsAction};
+    use crate::model::time::RestaurantTimezone;
+
+    /// UTC+1 in winter, so local midnight is not UTC midnight.
+    const BERLIN: RestaurantTimezone = RestaurantTimezone(chrono_tz::Europe::Berlin);
+
+    fn parse_before(before: &str) -> super::TimeBound {
+        match Cli::try_parse_from(["simple-restaurant-api", "items", "purge", "--before", before]) {
+            Ok(Cli { command: Some(Command::Items { action: ItemsAction::Purge { before, .. } }), .. }) => before,
+            Ok(_) => panic!("not an items purge"),
+            Err(e) => panic!("{e}"),
+        }
+    }
+
+    #[test]
+    fn purges_before_the_restaurants_midnight_of_a_date() {
+        assert_eq!(BERLIN.resolve(&parse_before("2024-01-01")), Utc.with_ymd_and_hms(2023, 12, 31, 23, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn purges_before_a_timestamp() {
+        assert_eq!(BERLIN.resolve(&parse_before("2024-01-01T06:30:00")), Utc.with_ymd_and_hms(2024, 1, 1, 5, 30, 0).unwrap());
+        assert_eq!(BERLIN.resolve(&parse_before("2024-01-01T06:30:00Z")), Utc.with_ymd_and_hms(2024, 1, 1, 6, 30, 0).unwrap());
+    }
+
+    #[test]
+    fn rejects_other_cutoffs() {
+        assert!(Cli::try_parse_from(["simple-restaurant-api", "items", "purge", "--before", "yesterday"]).is_err());
+    }
+}
@@ -0,0 +1,39 @@
+use std::convert::Infallible;
+use std::fmt::{Display, Formatter};
+use actix_web::{dev::Payload, FromRequest, HttpRequest};
+use std::future::{ready, Ready};
+
+const ACTOR_HEADER: &str = "x-actor";
+const ANONYMOUS_ACTOR: &str = "anonymous";
+const MAX_ACTOR_LENGTH: usize = 127;
+
+/// Who performed a request, as reported by the `X-Actor` header (e.g. the waiter's tablet).
+#[derive(Debug, Clone)]
+pub struct Actor(String);
+
+impl Actor {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for Actor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromRequest for Actor {
+    type Error = Infallible;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let actor = req.headers().get(ACTOR_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(|value| value.chars().take(MAX_ACTOR_LENGTH).collect())
+            .unwrap_or_else(|| ANONYMOUS_ACTOR.to_string());
+        ready(Ok(Actor(actor)))
+    }
+}
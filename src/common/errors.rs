@@ -1,9 +1,21 @@
-use actix_web::{HttpResponse, ResponseError};
+use actix_request_identifier::RequestId;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::error::BlockingError;
+use actix_web::http::header::RETRY_AFTER;
+use actix_web::http::StatusCode;
+use actix_web::middleware::Next;
+use actix_web::{HttpMessage, HttpResponse, ResponseError};
 use derive_more::{Display, Error, From};
-use log::{error};
+use tracing::{error, warn};
 use serde::Serialize;
+use utoipa::ToSchema;
 use mysql::Error;
 
+const PROBLEM_CONTENT_TYPE: &str = "application/problem+json";
+/// Sent with `503`, a pool connection or the contended rows are usually free again by then.
+const RETRY_AFTER_SECONDS: u32 = 1;
+
 #[derive(Debug, Display, Error, From)]
 pub enum PersistenceError {
     DBConnError,
@@ -11,58 +23,208 @@ pub enum PersistenceError {
     CommitError,
     RollbackError,
     ResourceNotFound,
+    ResourceConflict,
+    VersionMismatch,
+    IdempotencyKeyMismatch,
     DBOpError,
+    /// Still failing after the retries.
+    Deadlock,
+    LockWaitTimeout,
 }
 
-#[derive(Debug, Display, Error, Serialize)]
-pub struct PersistenceErrorResponse {
-    message: String,
+/// Errors surfaced by the HTTP API, rendered as RFC 7807 `application/problem+json`.
+#[derive(Debug, Display)]
+pub enum AppError {
+    #[display(fmt = "Request validation failed")]
+    Validation(Vec<FieldError>),
+    #[display(fmt = "{}", _0)]
+    NotFound(String),
+    #[display(fmt = "{}", _0)]
+    Conflict(String),
+    #[display(fmt = "Bulk operation rolled back")]
+    BulkRolledBack(Vec<FieldError>),
+    #[display(fmt = "{}", _0)]
+    PreconditionFailed(String),
+    #[display(fmt = "{}", _0)]
+    PreconditionRequired(String),
+    // Nothing authenticates requests yet; the variant keeps the error codes stable once it does.
+    #[allow(dead_code)]
+    #[display(fmt = "{}", _0)]
+    Unauthorized(String),
+    #[display(fmt = "{}", _0)]
+    Storage(PersistenceError),
+    #[display(fmt = "Internal server error")]
+    Internal,
 }
 
-impl ResponseError for PersistenceError {
-    fn error_response(&self) -> HttpResponse {
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { field: field.into(), message: message.into() }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    pub code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+impl AppError {
+    /// Stable, machine-readable identifier of the error; clients branch on this, never on `detail`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Validation(_) => "validation_failed",
+            AppError::NotFound(_) => "resource_not_found",
+            AppError::Conflict(_) => "resource_conflict",
+            AppError::BulkRolledBack(_) => "bulk_rolled_back",
+            AppError::PreconditionFailed(_) => "precondition_failed",
+            AppError::PreconditionRequired(_) => "precondition_required",
+            AppError::Unauthorized(_) => "unauthorized",
+            AppError::Storage(PersistenceError::DBConnError) => "storage_unavailable",
+            AppError::Storage(PersistenceError::TransactionStartError) => "storage_transaction_start_failed",
+            AppError::Storage(PersistenceError::CommitError) => "storage_commit_failed",
+            AppError::Storage(PersistenceError::RollbackError) => "storage_rollback_failed",
+            AppError::Storage(PersistenceError::Deadlock | PersistenceError::LockWaitTimeout) => "storage_contention",
+            AppError::Storage(_) => "storage_operation_failed",
+            AppError::Internal => "internal_error",
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            AppError::Validation(_) => "Invalid request",
+            AppError::NotFound(_) => "Resource not found",
+            AppError::Conflict(_) => "Resource conflict",
+            AppError::BulkRolledBack(_) => "Bulk operation rolled back",
+            AppError::PreconditionFailed(_) => "Precondition failed",
+            AppError::PreconditionRequired(_) => "Precondition required",
+            AppError::Unauthorized(_) => "Unauthorized",
+            AppError::Storage(_) => "Storage error",
+            AppError::Internal => "Internal server error",
+        }
+    }
+
+    fn detail(&self) -> String {
+        match self {
+            AppError::Storage(PersistenceError::DBConnError) => "No database connection available, retry later".into(),
+            AppError::Storage(PersistenceError::TransactionStartError) => "Can NOT begin the transaction".into(),
+            AppError::Storage(PersistenceError::CommitError) => "Can NOT commit the transaction".into(),
+            AppError::Storage(PersistenceError::RollbackError) => "Can NOT rollback the transaction".into(),
+            AppError::Storage(PersistenceError::Deadlock | PersistenceError::LockWaitTimeout) =>
+                "Concurrent requests kept conflicting on the same rows, retry later".into(),
+            AppError::Storage(_) => "Can NOT Execute SQL".into(),
+            other => other.to_string(),
+        }
+    }
+
+    pub fn problem(&self, request_id: Option<&RequestId>) -> ProblemDetails {
+        ProblemDetails {
+            problem_type: format!("/problems/{}", self.code()),
+            title: self.title().into(),
+            status: self.status_code().as_u16(),
+            detail: self.detail(),
+            code: self.code().into(),
+            request_id: request_id.map(|request_id| request_id.as_str().into()),
+            errors: match self {
+                AppError::Validation(errors) | AppError::BulkRolledBack(errors) => errors.clone(),
+                _ => vec![],
+            },
+        }
+    }
+
+    fn problem_response(&self, request_id: Option<&RequestId>) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        if self.status_code() == StatusCode::SERVICE_UNAVAILABLE {
+            response.insert_header((RETRY_AFTER, RETRY_AFTER_SECONDS));
+        }
+        response.content_type(PROBLEM_CONTENT_TYPE).json(self.problem(request_id))
+    }
+}
+
+impl From<PersistenceError> for AppError {
+    fn from(err: PersistenceError) -> Self {
+        match err {
+            PersistenceError::ResourceNotFound => AppError::NotFound("Can NOT found the resource".into()),
+            PersistenceError::ResourceConflict => AppError::Conflict("The resource is not in a state allowing this operation".into()),
+            PersistenceError::VersionMismatch => AppError::PreconditionFailed("The resource was modified since it was fetched".into()),
+            PersistenceError::IdempotencyKeyMismatch => AppError::Conflict("Idempotency-Key was already used with a different request".into()),
+            other => AppError::Storage(other),
+        }
+    }
+}
+
+impl From<BlockingError> for AppError {
+    fn from(_: BlockingError) -> Self {
+        AppError::Internal
+    }
+}
+
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
         match self {
-            PersistenceError::DBConnError => {
-                let msg = "No connections available from pool";
-                error!("{}", msg);
-                HttpResponse::InternalServerError().json(PersistenceErrorResponse { message: msg.into() })
-            }
-
-            PersistenceError::TransactionStartError => {
-                let msg = "Can NOT begin the transaction";
-                error!("{}", msg);
-                HttpResponse::InternalServerError().json(PersistenceErrorResponse { message: msg.into() })
-            }
-
-            PersistenceError::CommitError => {
-                let msg = "Can NOT commit the transaction";
-                error!("{}", msg);
-                HttpResponse::InternalServerError().json(PersistenceErrorResponse { message: msg.into() })
-            }
-
-            PersistenceError::RollbackError => {
-                let msg = "Can NOT rollback the transaction";
-                error!("{}", msg);
-                HttpResponse::InternalServerError().json(PersistenceErrorResponse { message: msg.into() })
-            }
-
-            PersistenceError::ResourceNotFound => {
-                let msg = "Can NOT found the resource";
-                error!("{}", msg);
-                HttpResponse::NotFound().json(PersistenceErrorResponse { message: msg.into() })
-            }
-
-            PersistenceError::DBOpError => {
-                let msg = "Can NOT Execute SQL";
-                error!("{}", msg);
-                HttpResponse::InternalServerError().json(PersistenceErrorResponse { message: msg.into() })
-            }
+            AppError::Validation(_) => StatusCode::BAD_REQUEST,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) | AppError::BulkRolledBack(_) => StatusCode::CONFLICT,
+            AppError::PreconditionFailed(_) => StatusCode::PRECONDITION_FAILED,
+            AppError::PreconditionRequired(_) => StatusCode::PRECONDITION_REQUIRED,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Storage(PersistenceError::DBConnError | PersistenceError::Deadlock | PersistenceError::LockWaitTimeout) =>
+                StatusCode::SERVICE_UNAVAILABLE,
+            AppError::Storage(_) | AppError::Internal => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
+
+    fn error_response(&self) -> HttpResponse {
+        if self.status_code().is_server_error() {
+            error!(code = self.code(), detail = self.detail(), "Request failed");
+        } else {
+            warn!(code = self.code(), detail = self.detail(), "Request rejected");
+        }
+        self.problem_response(None)
+    }
+}
+
+/// Re-renders [`AppError`] responses with the request id, which `error_response` can not see.
+pub async fn attach_request_id(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let res = next.call(req).await?;
+    let problem = res.response().error()
+        .and_then(|err| err.as_error::<AppError>())
+        .map(|app_error| {
+            let request_id = res.request().extensions().get::<RequestId>().cloned();
+            app_error.problem_response(request_id.as_ref())
+        });
+
+    Ok(match problem {
+        Some(problem) => res.into_response(problem).map_into_right_body(),
+        None => res.map_into_left_body(),
+    })
+}
+
+/// Turns extractor (JSON body, query string, path) failures into validation problems.
+pub fn extractor_error(location: &str, err: impl std::fmt::Display) -> actix_web::Error {
+    AppError::Validation(vec![FieldError::new(location, err.to_string())]).into()
 }
 
 
 #[derive(Debug, Display, Error, From)]
+#[allow(clippy::enum_variant_names)]
 pub enum MysqlValueError {
     MissingString,
     MissingInteger,
@@ -78,4 +240,4 @@ pub fn generate_mysql_value_error(err_type: MysqlValueError, column_name: String
         },
         message: format!("Error: Issue with value existing in column({column_name})"),
     })
-}
\ No newline at end of file
+}
@@ -0,0 +1,88 @@
+use actix_web::http::header::{EntityTag, IfMatch, IfNoneMatch};
+use actix_web::{HttpMessage, HttpRequest};
+
+use crate::common::errors::AppError;
+use crate::common::representation::Representation;
+use crate::model::resources::TableItem;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// Versions of an item the client is allowed to modify, from its `If-Match` header.
+pub enum Precondition {
+    Any,
+    Versions(Vec<u32>),
+}
+
+impl Precondition {
+    pub fn matches(&self, version: u32) -> bool {
+        match self {
+            Precondition::Any => true,
+            Precondition::Versions(versions) => versions.contains(&version),
+        }
+    }
+}
+
+/// `"{item_id}-{version}"`, suffixed with the representation unless it is plain JSON: each rendering
+/// is a different body, so a tag taken from one must not validate another.
+pub fn item_etag(item: &TableItem, representation: Representation) -> EntityTag {
+    tagged(format!("{}-{}", item.item_id, item.version), representation)
+}
+
+/// Changes whenever an item of the table is added, voided, restored or updated.
+pub fn table_etag(table_number: u32, items: &[TableItem], representation: Representation) -> EntityTag {
+    let mut hash = FNV_OFFSET_BASIS;
+    for item in items {
+        for byte in format!("{}:{};", item.item_id, item.version).bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+    tagged(format!("{table_number}-{hash:016x}"), representation)
+}
+
+fn tagged(tag: String, representation: Representation) -> EntityTag {
+    match representation.etag_suffix() {
+        Some(suffix) => EntityTag::new_strong(format!("{tag}-{suffix}")),
+        None => EntityTag::new_strong(tag),
+    }
+}
+
+/// `true` when the client already holds the current representation and `304` can be returned.
+pub fn is_not_modified(req: &HttpRequest, etag: &EntityTag) -> bool {
+    match req.get_header::<IfNoneMatch>() {
+        Some(IfNoneMatch::Any) => true,
+        Some(IfNoneMatch::Items(etags)) => etags.iter().any(|candidate| candidate.weak_eq(etag)),
+        None => false,
+    }
+}
+
+/// Mutating item endpoints require `If-Match` with the item's current ETag.
+pub fn item_precondition(req: &HttpRequest, item_id: u32) -> Result<Precondition, AppError> {
+    match req.get_header::<IfMatch>() {
+        Some(IfMatch::Any) => Ok(Precondition::Any),
+        Some(IfMatch::Items(etags)) => Ok(Precondition::Versions(
+            etags.iter()
+                .filter(|etag| !etag.weak)
+                .filter_map(|etag| parse_item_version(etag.tag(), item_id))
+                .collect(),
+        )),
+        None => Err(AppError::PreconditionRequired(
+            "If-Match header with the item's ETag is required".into(),
+        )),
+    }
+}
+
+/// Only the version counts: a tag of any representation of the item is a valid `If-Match`.
+fn parse_item_version(tag: &str, item_id: u32) -> Option<u32> {
+    let mut parts = tag.splitn(3, '-');
+    if parts.next()?.parse::<u32>().ok()? != item_id {
+        return None;
+    }
+    let version = parts.next()?.parse().ok()?;
+    match parts.next() {
+        None => Some(version),
+        Some(suffix) if Representation::from_etag_suffix(suffix).is_some() => Some(version),
+        Some(_) => None,
+    }
+}
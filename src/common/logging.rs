@@ -0,0 +1,153 @@
+//! One JSON object per log line on stderr. Lines emitted while serving a request carry its
+//! context from the request span: request id, method, route, table number, item id and the
+//! latency so far.
+use std::fmt::Debug;
+use std::io::Write;
+use std::time::Instant;
+
+use chrono::{SecondsFormat, Utc};
+use serde_json::{Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Subscriber};
+use tracing_log::NormalizeEvent;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// Name of the span opened for every request by `trace_request`.
+pub(crate) const REQUEST_SPAN: &str = "request";
+
+/// Request span fields copied onto the log lines, with the name they are logged under.
+const CONTEXT_FIELDS: [(&str, &str); 5] = [
+    ("request_id", "request_id"),
+    ("http.request.method", "method"),
+    ("http.route", "route"),
+    ("table_number", "table_number"),
+    ("item_id", "item_id"),
+];
+
+pub struct JsonLogLayer;
+
+/// Kept in the request span's extensions.
+struct RequestContext {
+    started: Instant,
+    fields: Map<String, Value>,
+}
+
+impl<S> Layer<S> for JsonLogLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if attrs.metadata().name() != REQUEST_SPAN {
+            return;
+        }
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut context = RequestContext { started: Instant::now(), fields: Map::new() };
+        attrs.record(&mut ContextVisitor(&mut context.fields));
+        span.extensions_mut().insert(context);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        if let Some(context) = extensions.get_mut::<RequestContext>() {
+            values.record(&mut ContextVisitor(&mut context.fields));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        // Events bridged from `log` carry their real target in fields.
+        let normalized = event.normalized_metadata();
+        let metadata = normalized.as_ref().unwrap_or_else(|| event.metadata());
+
+        let mut line = Map::new();
+        line.insert("timestamp".into(), Utc::now().to_rfc3339_opts(SecondsFormat::Micros, true).into());
+        line.insert("level".into(), metadata.level().as_str().into());
+        line.insert("target".into(), metadata.target().into());
+        event.record(&mut EventVisitor(&mut line));
+
+        let request_span = ctx.event_scope(event)
+            .and_then(|mut scope| scope.find(|span| span.extensions().get::<RequestContext>().is_some()));
+        if let Some(span) = request_span {
+            let extensions = span.extensions();
+            if let Some(context) = extensions.get::<RequestContext>() {
+                for (name, value) in &context.fields {
+                    line.entry(name.clone()).or_insert_with(|| value.clone());
+                }
+                // Microsecond resolution, as for the health checks.
+                let latency_ms = (context.started.elapsed().as_secs_f64() * 1_000_000.0).round() / 1000.0;
+                line.insert("latency_ms".into(), latency_ms.into());
+            }
+        }
+
+        let mut out = std::io::stderr().lock();
+        let _ = serde_json::to_writer(&mut out, &line).map(|_| writeln!(out));
+    }
+}
+
+struct ContextVisitor<'a>(&'a mut Map<String, Value>);
+
+impl ContextVisitor<'_> {
+    fn insert(&mut self, field: &Field, value: Value) {
+        if let Some((_, name)) = CONTEXT_FIELDS.iter().find(|(span_field, _)| *span_field == field.name()) {
+            self.0.insert(name.to_string(), value);
+        }
+    }
+}
+
+impl Visit for ContextVisitor<'_> {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.insert(field, format!("{value:?}").into());
+    }
+}
+
+struct EventVisitor<'a>(&'a mut Map<String, Value>);
+
+impl EventVisitor<'_> {
+    fn insert(&mut self, field: &Field, value: Value) {
+        // `log.target`, `log.line` and the like are already in the normalized metadata.
+        if !field.name().starts_with("log.") {
+            self.0.insert(field.name().into(), value);
+        }
+    }
+}
+
+impl Visit for EventVisitor<'_> {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.insert(field, format!("{value:?}").into());
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::HashSet;
+use std::sync::{LazyLock, RwLock};
+use std::time::Instant;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use prometheus::{Encoder, Histogram, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder};
+
+use crate::model::resources::VoidReason;
+
+/// Label of the items not on the menu, which keeps free-text names out of the label values.
+const OTHER_ITEM: &str = "other";
+pub(crate) const UNMATCHED_ROUTE: &str = "unmatched";
+
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    pub(crate) db_pool_wait: Histogram,
+    pub(crate) db_connections_in_use: IntGauge,
+    pub(crate) db_function_duration: HistogramVec,
+    pub(crate) db_open_transactions: IntGauge,
+    pub(crate) db_transactions: IntCounterVec,
+    pub(crate) db_retries: IntCounterVec,
+    pub(crate) db_retries_exhausted: IntCounterVec,
+    items_ordered: IntCounterVec,
+    items_removed: IntCounterVec,
+    /// Lowercase names of the menu dishes, loaded on startup.
+    menu: RwLock<HashSet<String>>,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests by route pattern, method and status"),
+            &["method", "route", "status"],
+        ).unwrap();
+        let http_request_duration = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "HTTP request latency by route pattern, method and status"),
+            &["method", "route", "status"],
+        ).unwrap();
+        let db_pool_wait = Histogram::with_opts(HistogramOpts::new(
+            "db_pool_wait_seconds", "Time spent waiting for a pool connection",
+        )).unwrap();
+        let db_connections_in_use = IntGauge::new(
+            "db_connections_in_use", "Pool connections checked out by persistence functions",
+        ).unwrap();
+        let db_function_duration = HistogramVec::new(
+            HistogramOpts::new("db_function_duration_seconds", "Time a persistence function holds its connection"),
+            &["function"],
+        ).unwrap();
+        let db_open_transactions = IntGauge::new(
+            "db_open_transactions", "Transactions begun and not yet committed or rolled back",
+        ).unwrap();
+        let db_transactions = IntCounterVec::new(
+            Opts::new("db_transactions_total", "Transactions by persistence function and how they ended"),
+            &["function", "outcome"],
+        ).unwrap();
+        let db_retries = IntCounterVec::new(
+            Opts::new("db_transaction_retries_total", "Transactions run again after a deadlock or lock wait timeout"),
+            &["function", "error"],
+        ).unwrap();
+        let db_retries_exhausted = IntCounterVec::new(
+            Opts::new("db_transaction_retries_exhausted_total", "Transactions given up on after the last retry"),
+            &["function", "error"],
+        ).unwrap();
+        let items_ordered = IntCounterVec::new(
+            Opts::new("items_ordered_total", "Items added to tables, by menu dish"),
+            &["item"],
+        ).unwrap();
+        let items_removed = IntCounterVec::new(
+            Opts::new("items_removed_total", "Items voided, by menu dish and reason"),
+            &["item", "reason"],
+        ).unwrap();
+
+        registry.register(Box::new(http_requests.clone())).unwrap();
+        registry.register(Box::new(http_request_duration.clone())).unwrap();
+        registry.register(Box::new(db_pool_wait.clone())).unwrap();
+        registry.register(Box::new(db_connections_in_use.clone())).unwrap();
+        registry.register(Box::new(db_function_duration.clone())).unwrap();
+        registry.register(Box::new(db_open_transactions.clone())).unwrap();
+        registry.register(Box::new(db_transactions.clone())).unwrap();
+        registry.register(Box::new(db_retries.clone())).unwrap();
+        registry.register(Box::new(db_retries_exhausted.clone())).unwrap();
+        registry.register(Box::new(items_ordered.clone())).unwrap();
+        registry.register(Box::new(items_removed.clone())).unwrap();
+
+        Metrics {
+            registry,
+            http_requests,
+            http_request_duration,
+            db_pool_wait,
+            db_connections_in_use,
+            db_function_duration,
+            db_open_transactions,
+            db_transactions,
+            db_retries,
+            db_retries_exhausted,
+            items_ordered,
+            items_removed,
+            menu: RwLock::new(HashSet::new()),
+        }
+    }
+
+    pub fn set_menu(&self, items_names: Vec<String>) {
+        let menu = items_names.into_iter().map(|item_name| item_name.to_lowercase()).collect();
+        *self.menu.write().unwrap_or_else(|e| e.into_inner()) = menu;
+    }
+
+    pub fn item_ordered(&self, item_name: &str) {
+        self.items_ordered.with_label_values(&[&self.item_label(item_name)]).inc();
+    }
+
+    pub fn item_removed(&self, item_name: &str, reason: VoidReason) {
+        self.items_removed.with_label_values(&[&self.item_label(item_name), reason.as_str()]).inc();
+    }
+
+    fn item_label(&self, item_name: &str) -> String {
+        let item_name = item_name.to_lowercase();
+        let menu = self.menu.read().unwrap_or_else(|e| e.into_inner());
+        if menu.contains(&item_name) { item_name } else { OTHER_ITEM.to_string() }
+    }
+
+    /// Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer).unwrap_or_default();
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+/// Counts and times requests by route pattern, e.g. `/v1/tables/{table_number}`, never by raw path.
+pub async fn record_request_metrics(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let started = Instant::now();
+    let method = req.method().to_string();
+    let route = req.match_pattern().unwrap_or_else(|| UNMATCHED_ROUTE.to_string());
+
+    let res = next.call(req).await;
+    let status = match &res {
+        Ok(res) => res.status(),
+        Err(err) => err.as_response_error().status_code(),
+    };
+    let labels = [method.as_str(), route.as_str(), status.as_str()];
+    METRICS.http_requests.with_label_values(&labels).inc();
+    METRICS.http_request_duration.with_label_values(&labels).observe(started.elapsed().as_secs_f64());
+    res
+}
@@ -1 +1,9 @@
-pub mod errors;
\ No newline at end of file
+pub mod actor;
+pub mod errors;
+pub mod etag;
+pub mod logging;
+pub mod metrics;
+pub mod representation;
+pub mod routes;
+pub mod shutdown;
+pub mod telemetry;
\ No newline at end of file
@@ -0,0 +1,167 @@
+use std::convert::Infallible;
+use std::future::{ready, Ready};
+use actix_web::dev::Payload;
+use actix_web::http::header::{Accept, VARY};
+use actix_web::{FromRequest, HttpMessage, HttpRequest, HttpResponse, HttpResponseBuilder};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+const HAL_CONTENT_TYPE: &str = "application/hal+json";
+const JSON_API_CONTENT_TYPE: &str = "application/vnd.api+json";
+const ITEM_KIND: Kind = Kind { name: "items", id_field: "item_id" };
+
+/// JSON:API type of a resource and the attribute holding its id.
+#[derive(Debug, Clone, Copy)]
+pub struct Kind {
+    pub name: &'static str,
+    pub id_field: &'static str,
+}
+
+/// A resource with `_links`, optionally embedding item resources under `items`.
+pub trait Hypermedia: Serialize {
+    /// `None` for collections, which have no identity of their own.
+    const KIND: Option<Kind>;
+}
+
+/// Representation asked for in `Accept`; plain JSON unless HAL or JSON:API is preferred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Representation {
+    Json,
+    Hal,
+    JsonApi,
+}
+
+impl Representation {
+    pub fn respond<T: Hypermedia>(&self, builder: &mut HttpResponseBuilder, resource: &T) -> HttpResponse {
+        builder.insert_header((VARY, "Accept"));
+        match self {
+            Representation::Json => builder.json(resource),
+            Representation::Hal => builder.content_type(HAL_CONTENT_TYPE)
+                .json(to_hal(serde_json::to_value(resource).unwrap_or_default())),
+            Representation::JsonApi => builder.content_type(JSON_API_CONTENT_TYPE)
+                .json(to_json_api(serde_json::to_value(resource).unwrap_or_default(), T::KIND)),
+        }
+    }
+
+    /// Told apart in ETags; plain JSON keeps the tags it had before negotiation existed.
+    pub fn etag_suffix(&self) -> Option<&'static str> {
+        match self {
+            Representation::Json => None,
+            Representation::Hal => Some("hal"),
+            Representation::JsonApi => Some("jsonapi"),
+        }
+    }
+
+    pub fn from_etag_suffix(suffix: &str) -> Option<Self> {
+        [Representation::Hal, Representation::JsonApi].into_iter()
+            .find(|representation| representation.etag_suffix() == Some(suffix))
+    }
+}
+
+impl FromRequest for Representation {
+    type Error = Infallible;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let representation = req.get_header::<Accept>()
+            .and_then(|accept| accept.ranked().iter().find_map(|mime| match mime.essence_str() {
+                HAL_CONTENT_TYPE => Some(Representation::Hal),
+                JSON_API_CONTENT_TYPE => Some(Representation::JsonApi),
+                "application/json" | "application/*" | "*/*" => Some(Representation::Json),
+                _ => None,
+            }))
+            .unwrap_or(Representation::Json);
+        ready(Ok(representation))
+    }
+}
+
+/// `_links` becomes a map keyed by rel and embedded items move to `_embedded`.
+fn to_hal(value: Value) -> Value {
+    let Value::Object(mut resource) = value else { return value };
+    if let Some(Value::Array(links)) = resource.remove("_links") {
+        let links: Map<String, Value> = links.into_iter()
+            .filter_map(|link| Some((link.get("rel")?.as_str()?.to_string(), link_object(&link)?)))
+            .collect();
+        resource.insert("_links".into(), Value::Object(links));
+    }
+    if let Some(Value::Array(items)) = resource.remove("items") {
+        let items: Vec<Value> = items.into_iter().map(to_hal).collect();
+        resource.insert("_embedded".into(), json!({ "items": items }));
+    }
+    Value::Object(resource)
+}
+
+/// Collections become a `data` array; single resources a `data` object whose items are
+/// referenced in `relationships` and rendered in `included`.
+fn to_json_api(value: Value, kind: Option<Kind>) -> Value {
+    let Value::Object(mut resource) = value else { return value };
+    let links = resource.remove("_links").map(json_api_links);
+    let items: Option<Vec<Value>> = match resource.remove("items") {
+        Some(Value::Array(items)) => Some(items.into_iter().map(|item| json_api_resource(item, ITEM_KIND)).collect()),
+        _ => None,
+    };
+
+    let mut document = Map::new();
+    let Some(kind) = kind else {
+        document.insert("data".into(), Value::Array(items.unwrap_or_default()));
+        if let Some(links) = links {
+            document.insert("links".into(), links);
+        }
+        return Value::Object(document);
+    };
+    let mut data = json_api_resource(Value::Object(resource), kind);
+    if let (Some(items), Value::Object(data)) = (items, &mut data) {
+        let identifiers: Vec<Value> = items.iter()
+            .map(|item| json!({ "type": item["type"], "id": item["id"] }))
+            .collect();
+        data.insert("relationships".into(), json!({ "items": { "data": identifiers } }));
+        document.insert("included".into(), Value::Array(items));
+    }
+    if let (Some(links), Value::Object(data)) = (links, &mut data) {
+        data.insert("links".into(), links);
+    }
+    document.insert("data".into(), data);
+    Value::Object(document)
+}
+
+fn json_api_resource(value: Value, kind: Kind) -> Value {
+    let Value::Object(mut attributes) = value else { return value };
+    let id = match attributes.remove(kind.id_field) {
+        Some(Value::String(id)) => id,
+        Some(id) => id.to_string(),
+        None => String::new(),
+    };
+    let mut resource = json!({ "type": kind.name, "id": id });
+    if let Some(links) = attributes.remove("_links") {
+        resource["links"] = json_api_links(links);
+    }
+    if !attributes.is_empty() {
+        resource["attributes"] = Value::Object(attributes);
+    }
+    resource
+}
+
+fn json_api_links(links: Value) -> Value {
+    let Value::Array(links) = links else { return links };
+    Value::Object(links.into_iter()
+        .filter_map(|link| {
+            let rel = link.get("rel")?.as_str()?.to_string();
+            let object = link_object(&link)?;
+            // Plain GET links are bare URLs in JSON:API; others keep their method as meta.
+            let link = match object.get("method") {
+                Some(method) => json!({ "href": object["href"], "meta": { "method": method } }),
+                None => object["href"].clone(),
+            };
+            Some((rel, link))
+        })
+        .collect())
+}
+
+/// `{ href }`, plus the method when following the link is not a GET.
+fn link_object(link: &Value) -> Option<Value> {
+    let href = link.get("href")?.clone();
+    match link.get("method").and_then(Value::as_str) {
+        Some(method) if method != "Get" => Some(json!({ "href": href, "method": method.to_uppercase() })),
+        _ => Some(json!({ "href": href })),
+    }
+}
@@ -0,0 +1,116 @@
+use std::convert::Infallible;
+use std::future::{ready, Ready};
+use actix_web::dev::Payload;
+use actix_web::http::Method;
+use actix_web::{web, FromRequest, Handler, HttpRequest, Responder};
+use hateoas::{HttpMethod, RelLink};
+
+/// Every route the API serves. Handlers are registered from this table and resources build
+/// their links from it, so a link can only point at a route that is actually served.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Route {
+    Table,
+    TableItems,
+    AddItems,
+    Item,
+    VoidItem,
+    RestoreItem,
+    BulkVoidItems,
+    BulkUpdateStatus,
+    AuditLog,
+    VoidedItemsReport,
+}
+
+impl Route {
+    pub fn method(&self) -> Method {
+        match self {
+            Route::Table | Route::TableItems | Route::Item | Route::AuditLog | Route::VoidedItemsReport => Method::GET,
+            Route::AddItems | Route::RestoreItem | Route::BulkVoidItems | Route::BulkUpdateStatus => Method::POST,
+            Route::VoidItem => Method::DELETE,
+        }
+    }
+
+    pub fn pattern(&self) -> &'static str {
+        match self {
+            Route::Table => "/tables/{table_number}",
+            Route::TableItems | Route::AddItems => "/tables/{table_number}/items",
+            Route::Item | Route::VoidItem => "/tables/{table_number}/items/{item_id}",
+            Route::RestoreItem => "/tables/{table_number}/items/{item_id}/restore",
+            Route::BulkVoidItems => "/tables/{table_number}/items/bulk-void",
+            Route::BulkUpdateStatus => "/tables/{table_number}/items/bulk-status",
+            Route::AuditLog => "/audit",
+            Route::VoidedItemsReport => "/reports/voided-items",
+        }
+    }
+
+    /// Fills the `{…}` segments of the pattern, in order, with `params`.
+    fn path(&self, params: &[u32]) -> String {
+        let mut params = params.iter();
+        let path = self.pattern().split('/')
+            .map(|segment| match segment.starts_with('{') {
+                true => params.next().map(u32::to_string).unwrap_or_default(),
+                false => segment.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        debug_assert!(params.next().is_none(), "too many params for {}", self.pattern());
+        path
+    }
+}
+
+pub fn register<F, Args>(cfg: &mut web::ServiceConfig, route: Route, handler: F)
+where
+    F: Handler<Args>,
+    Args: FromRequest + 'static,
+    F::Output: Responder + 'static,
+{
+    cfg.route(route.pattern(), web::method(route.method()).to(handler));
+}
+
+/// Path prefix the routes of an API version are mounted under. The unversioned aliases have none.
+#[derive(Debug, Clone, Copy)]
+pub struct Mount(pub &'static str);
+
+/// Builds links to routes of the mount that served the current request.
+pub struct Links {
+    mount: &'static str,
+}
+
+impl Links {
+    pub fn href(&self, route: Route, params: &[u32]) -> String {
+        format!("{}{}", self.mount, route.path(params))
+    }
+
+    pub fn link(&self, rel: &str, route: Route, params: &[u32]) -> RelLink {
+        self.link_with_query(rel, route, params, "")
+    }
+
+    /// `query` is appended without its leading `?`; an empty one adds nothing.
+    pub fn link_with_query(&self, rel: &str, route: Route, params: &[u32], query: &str) -> RelLink {
+        let href = match query.is_empty() {
+            true => self.href(route, params),
+            false => format!("{}?{query}", self.href(route, params)),
+        };
+        RelLink::new(rel, &href, link_method(&route.method()))
+    }
+}
+
+impl FromRequest for Links {
+    type Error = Infallible;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let mount = req.app_data::<Mount>().map(|mount| mount.0).unwrap_or_default();
+        ready(Ok(Links { mount }))
+    }
+}
+
+fn link_method(method: &Method) -> HttpMethod {
+    match *method {
+        Method::POST => HttpMethod::Post,
+        Method::PUT => HttpMethod::Put,
+        Method::DELETE => HttpMethod::Delete,
+        Method::PATCH => HttpMethod::Patch,
+        _ => HttpMethod::Get,
+    }
+}
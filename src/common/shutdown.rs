@@ -0,0 +1,125 @@
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use actix_web::dev::ServerHandle;
+use actix_web::web;
+use tokio::signal;
+use tracing::{info, warn};
+
+use crate::persist::connection::connections_in_use;
+
+const RELEASE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Shared by the signal handler, the readiness check and `main`.
+pub struct Shutdown {
+    delay: Duration,
+    timeout: Duration,
+    deadline: OnceLock<Instant>,
+}
+
+impl Shutdown {
+    pub fn new(delay: Duration, timeout: Duration) -> Self {
+        Shutdown { delay, timeout, deadline: OnceLock::new() }
+    }
+
+    /// Readiness fails from the signal on, so no new traffic is routed here.
+    pub fn is_draining(&self) -> bool {
+        self.deadline.get().is_some()
+    }
+
+    fn start(&self) -> Instant {
+        *self.deadline.get_or_init(|| Instant::now() + self.delay + self.timeout)
+    }
+
+    /// Waits, until the deadline, for everything else holding `shared` to let go of it: the stopped
+    /// workers' apps and the database work still running on the blocking threads, e.g. an
+    /// `add_items_to_table` transaction whose handler was already dropped. `None` once it passed.
+    pub async fn release<T>(&self, shared: web::Data<T>) -> Option<T> {
+        let deadline = self.start();
+        let mut shared = shared.into_inner();
+        loop {
+            match Arc::try_unwrap(shared) {
+                Ok(value) => return Some(value),
+                Err(still_shared) if Instant::now() < deadline => {
+                    shared = still_shared;
+                    actix_web::rt::time::sleep(RELEASE_POLL_INTERVAL).await;
+                }
+                Err(_) => {
+                    warn!(connections = connections_in_use(), "Shutdown deadline passed with database work in progress");
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+/// On SIGTERM or Ctrl-C: fails readiness, keeps serving for the shutdown delay, then stops
+/// accepting connections and waits for the in-flight requests until the shutdown deadline.
+pub async fn stop_on_signal(server: ServerHandle, shutdown: web::Data<Shutdown>) {
+    let signal = terminated().await;
+    let deadline = shutdown.start();
+    info!(signal, delay_seconds = shutdown.delay.as_secs(), timeout_seconds = shutdown.timeout.as_secs(), "Shutting down");
+    actix_web::rt::time::sleep(shutdown.delay).await;
+    server.stop(true).await;
+    if Instant::now() >= deadline {
+        warn!("Shutdown deadline passed with requests in flight");
+    }
+}
+
+#[cfg(unix)]
+async fn terminated() -> &'static str {
+    let mut terminate = signal::unix::signal(signal::unix::SignalKind::terminate())
+        .expect("SIGTERM handler can be installed");
+    tokio::select! {
+        _ = terminate.recv() => "SIGTERM",
+        _ = signal::ctrl_c() => "SIGINT",
+    }
+}
+
+#[cfg(not(unix))]
+async fn terminated() -> &'static str {
+    let _ = signal::ctrl_c().await;
+    "SIGINT"
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use actix_web::web;
+
+    use super::Shutdown;
+
+    #[actix_web::test]
+    async fn releases_once_the_work_in_flight_completes() {
+        let shutdown = Shutdown::new(Duration::ZERO, Duration::from_secs(5));
+        let shared = web::Data::new(());
+        let completed = Arc::new(AtomicBool::new(false));
+        let (in_flight, work_completed) = (shared.clone(), completed.clone());
+        let work = web::block(move || {
+            thread::sleep(Duration::from_millis(200));
+            work_completed.store(true, Ordering::SeqCst);
+            drop(in_flight);
+        });
+        assert!(!shutdown.is_draining());
+
+        let released = shutdown.release(shared).await;
+
+        assert!(shutdown.is_draining());
+        assert!(released.is_some());
+        assert!(completed.load(Ordering::SeqCst));
+        work.await.unwrap();
+    }
+
+    #[actix_web::test]
+    async fn gives_up_at_the_deadline() {
+        let shutdown = Shutdown::new(Duration::ZERO, Duration::from_millis(100));
+        let shared = web::Data::new(());
+        let _never_released = shared.clone();
+
+        assert!(shutdown.release(shared).await.is_none());
+    }
+}
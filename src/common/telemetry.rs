@@ -0,0 +1,158 @@
+use actix_request_identifier::RequestId;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ResourceDef, ServiceRequest, ServiceResponse};
+use actix_web::error::BlockingError;
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::{web, HttpMessage};
+use opentelemetry::propagation::Extractor;
+use opentelemetry::trace::{TraceContextExt, TracerProvider};
+use opentelemetry::global;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use tracing::field::Empty;
+use tracing::{info, info_span, Instrument, Level, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::filter::{filter_fn, FilterExt, Targets};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+use crate::common::logging::{JsonLogLayer, REQUEST_SPAN};
+use crate::common::metrics::UNMATCHED_ROUTE;
+use crate::config::{LogConfig, TelemetryConfig};
+
+/// Path parameters copied onto the request span.
+const TRACED_PARAMS: [&str; 2] = ["table_number", "item_id"];
+/// Probes and scrapes, left out of the request log.
+const QUIET_ROUTES: [&str; 3] = ["/health/live", "/health/ready", "/metrics"];
+
+/// Logs JSON lines, bridging the `log` records of dependencies, and exports spans to the
+/// OTLP collector when one is configured, honoring incoming W3C `traceparent` headers.
+pub fn init(log: &LogConfig, telemetry: Option<&TelemetryConfig>) -> Result<Option<SdkTracerProvider>, String> {
+    let log_filter = EnvFilter::try_new(&log.level).map_err(|e| format!("Invalid log level `{}`: {e}", log.level))?;
+    // Request spans are kept whatever the level, the log lines take their context from them.
+    let log_layer = JsonLogLayer.with_filter(log_filter.or(filter_fn(|metadata| metadata.name() == REQUEST_SPAN)));
+
+    let provider = telemetry
+        .and_then(|telemetry| telemetry.otlp_endpoint.as_ref().map(|endpoint| (telemetry, endpoint)))
+        .map(|(telemetry, endpoint)| tracer_provider(&telemetry.service_name, endpoint))
+        .transpose()?;
+    let trace_layer = provider.as_ref().map(|provider| {
+        tracing_opentelemetry::layer()
+            .with_tracer(provider.tracer(env!("CARGO_PKG_NAME")))
+            .with_filter(Targets::new().with_target(env!("CARGO_CRATE_NAME"), Level::TRACE))
+    });
+
+    tracing_subscriber::registry()
+        .with(log_layer)
+        .with(trace_layer)
+        .try_init()
+        .map_err(|e| format!("Can NOT install the tracing subscriber: {e}"))?;
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    Ok(provider)
+}
+
+fn tracer_provider(service_name: &str, endpoint: &str) -> Result<SdkTracerProvider, String> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+        .build()
+        .map_err(|e| format!("Can NOT create the OTLP exporter: {e}"))?;
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(service_name.to_string()).build())
+        .build())
+}
+
+/// Opens the request span, continuing the caller's trace when it sent a `traceparent`,
+/// logs the request once answered and adds a `traceresponse` header so clients can find the trace.
+pub async fn trace_request(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let parent = global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(req.headers())));
+    let method = req.method().to_string();
+    let pattern = req.match_pattern();
+    // Routing happens further down the chain, so the path parameters are matched here.
+    let mut path = req.match_info().clone();
+    if let Some(pattern) = &pattern {
+        ResourceDef::new(pattern.as_str()).capture_match_info(&mut path);
+    }
+    let route = pattern.unwrap_or_else(|| UNMATCHED_ROUTE.to_string());
+    let quiet = QUIET_ROUTES.contains(&route.as_str());
+    let request_id = req.extensions().get::<RequestId>().map(|request_id| request_id.to_string()).unwrap_or_default();
+    let span = info_span!(
+        REQUEST_SPAN,
+        otel.name = format!("{method} {route}"),
+        otel.kind = "server",
+        otel.status_code = Empty,
+        http.request.method = method,
+        http.route = route,
+        http.response.status_code = Empty,
+        request_id = request_id,
+        table_number = Empty,
+        item_id = Empty,
+    );
+    span.set_parent(parent);
+    for param in TRACED_PARAMS {
+        match path.get(param).map(|value| (value, value.parse::<u32>())) {
+            Some((_, Ok(number))) => span.record(param, number),
+            Some((value, Err(_))) => span.record(param, value),
+            None => &span,
+        };
+    }
+
+    let mut res = next.call(req).instrument(span.clone()).await?;
+    let status = res.status();
+    span.record("http.response.status_code", status.as_u16());
+    if status.is_server_error() {
+        span.record("otel.status_code", "ERROR");
+    }
+    if !quiet {
+        span.in_scope(|| info!(status = status.as_u16(), path = res.request().path(), "Request served"));
+    }
+    if let Some(traceresponse) = traceresponse(&span) {
+        res.headers_mut().insert(HeaderName::from_static("traceresponse"), traceresponse);
+    }
+    Ok(res)
+}
+
+/// `web::block` keeping the caller's span, so the persistence spans join the request trace.
+pub async fn block<F, R>(f: F) -> Result<R, BlockingError>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    let span = Span::current();
+    web::block(move || span.in_scope(f)).await
+}
+
+fn traceresponse(span: &Span) -> Option<HeaderValue> {
+    let context = span.context();
+    let span_context = context.span().span_context().clone();
+    if !span_context.is_valid() {
+        return None;
+    }
+    let traceresponse = format!(
+        "00-{}-{}-{:02x}",
+        span_context.trace_id(),
+        span_context.span_id(),
+        span_context.trace_flags().to_u8(),
+    );
+    HeaderValue::from_str(&traceresponse).ok()
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(HeaderName::as_str).collect()
+    }
+}
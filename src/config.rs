@@ -0,0 +1,349 @@
+//! Service configuration. Each setting is read from, in increasing priority: its default, the
+//! TOML file given with `--config`, then its environment variable (`.env` included).
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::str::FromStr;
+
+use chrono_tz::Tz;
+use serde::{Serialize, Serializer};
+use tracing_subscriber::EnvFilter;
+
+use crate::persist::idempotency_keys::IdempotencyConfig;
+
+const REDACTED: &str = "<redacted>";
+
+#[derive(Debug, Serialize)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub log: LogConfig,
+    pub idempotency: IdempotencyConfig,
+    pub restaurant: RestaurantConfig,
+    pub telemetry: TelemetryConfig,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+    pub workers: usize,
+    /// How long requests are still accepted after SIGTERM, with readiness failing, so that
+    /// load balancers stop routing here first.
+    pub shutdown_delay_seconds: u64,
+    /// How long a shutdown then waits for in-flight requests and database work.
+    pub shutdown_timeout_seconds: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DatabaseConfig {
+    pub host: String,
+    pub port: u16,
+    pub name: String,
+    pub user: String,
+    #[serde(serialize_with = "redact")]
+    pub password: String,
+    pub pool_min: usize,
+    pub pool_max: usize,
+    /// How long a request waits for a pool connection before answering `503`.
+    pub pool_timeout_seconds: u64,
+    pub connect_timeout_seconds: u64,
+    pub read_timeout_seconds: u64,
+    /// Connection attempts on startup, with exponential backoff in between.
+    pub connect_attempts: u32,
+    pub tls_mode: TlsMode,
+    /// CA certificate (PEM or DER) the server certificate is verified against, instead of the system roots.
+    pub tls_ca: Option<String>,
+}
+
+/// Same meaning as the MySQL client's `--ssl-mode`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TlsMode {
+    Disabled,
+    /// Encrypted, without verifying the server certificate.
+    Required,
+    /// Encrypted, the server certificate is verified but not its host name.
+    VerifyCa,
+    VerifyIdentity,
+}
+
+impl FromStr for TlsMode {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "disabled" => Ok(TlsMode::Disabled),
+            "required" => Ok(TlsMode::Required),
+            "verify_ca" => Ok(TlsMode::VerifyCa),
+            "verify_identity" => Ok(TlsMode::VerifyIdentity),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct LogConfig {
+    /// `tracing` filter directives, e.g. `info` or `info,actix_web=debug`.
+    pub level: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RestaurantConfig {
+    #[serde(serialize_with = "timezone_name")]
+    pub timezone: Tz,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TelemetryConfig {
+    /// Base URL of an OTLP/HTTP collector, e.g. `http://localhost:4318`; spans are not exported without it.
+    pub otlp_endpoint: Option<String>,
+    pub service_name: String,
+}
+
+/// Every invalid or missing setting, so they can all be fixed at once.
+#[derive(Debug)]
+pub struct ConfigError(pub Vec<String>);
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Invalid configuration:")?;
+        for problem in &self.0 {
+            writeln!(f, "  - {problem}")?;
+        }
+        Ok(())
+    }
+}
+
+struct Setting {
+    key: &'static str,
+    env: &'static str,
+    default: Option<&'static str>,
+}
+
+const fn setting(key: &'static str, env: &'static str, default: Option<&'static str>) -> Setting {
+    Setting { key, env, default }
+}
+
+const SETTINGS: &[Setting] = &[
+    setting("server.host", "HOST", Some("0.0.0.0")),
+    setting("server.port", "PORT", Some("8080")),
+    setting("server.workers", "WORKERS", None),
+    setting("server.shutdown_delay_seconds", "SHUTDOWN_DELAY_SECONDS", Some("0")),
+    setting("server.shutdown_timeout_seconds", "SHUTDOWN_TIMEOUT_SECONDS", Some("30")),
+    setting("database.host", "MYSQL_HOST", Some("localhost")),
+    setting("database.port", "MYSQL_PORT", Some("3306")),
+    setting("database.name", "MYSQL_DBNAME", Some("simple_restaurant")),
+    setting("database.user", "MYSQL_USER", None),
+    setting("database.password", "MYSQL_PASSWORD", None),
+    setting("database.pool_min", "MYSQL_POOL_MIN", Some("10")),
+    setting("database.pool_max", "MYSQL_POOL_MAX", Some("100")),
+    setting("database.pool_timeout_seconds", "MYSQL_POOL_TIMEOUT_SECONDS", Some("5")),
+    setting("database.connect_timeout_seconds", "MYSQL_CONNECT_TIMEOUT_SECONDS", Some("10")),
+    setting("database.read_timeout_seconds", "MYSQL_READ_TIMEOUT_SECONDS", Some("30")),
+    setting("database.connect_attempts", "MYSQL_CONNECT_ATTEMPTS", Some("8")),
+    setting("database.tls_mode", "MYSQL_TLS_MODE", Some("disabled")),
+    setting("database.tls_ca", "MYSQL_TLS_CA", None),
+    setting("log.level", "LOG_LEVEL", Some("info")),
+    setting("idempotency.ttl_seconds", "IDEMPOTENCY_TTL_SECONDS", Some("86400")),
+    setting("restaurant.timezone", "RESTAURANT_TIMEZONE", Some("UTC")),
+    setting("telemetry.otlp_endpoint", "OTEL_EXPORTER_OTLP_ENDPOINT", None),
+    setting("telemetry.service_name", "OTEL_SERVICE_NAME", Some("simple-restaurant-api")),
+];
+
+impl Config {
+    pub fn load(file: Option<&Path>) -> Result<Config, ConfigError> {
+        let mut problems = Vec::new();
+        let file_values = match file {
+            Some(path) => read_file(path, &mut problems),
+            None => BTreeMap::new(),
+        };
+        let values: BTreeMap<&str, (String, &str)> = SETTINGS.iter()
+            .filter_map(|setting| {
+                let value = env::var(setting.env).ok().map(|value| (value, setting.env))
+                    .or_else(|| file_values.get(setting.key).map(|value| (value.clone(), setting.key)))
+                    .or_else(|| setting.default.map(|value| (value.to_string(), setting.key)));
+                value.map(|value| (setting.key, value))
+            })
+            .collect();
+
+        let mut reader = Reader { values, problems };
+        let non_empty = |value: &String| !value.trim().is_empty();
+        let positive = |value: &u64| *value > 0;
+        let port = |value: &u16| *value > 0;
+        let server_host = reader.value("server.host", "a host name or address", non_empty);
+        let server_port = reader.value("server.port", "a port between 1 and 65535", port);
+        let workers = reader.value_or("server.workers", "a positive number", |workers: &usize| *workers > 0, default_workers);
+        let shutdown_delay = reader.value("server.shutdown_delay_seconds", "a number of seconds", |_: &u64| true);
+        let shutdown_timeout = reader.value("server.shutdown_timeout_seconds", "a number of seconds", |_: &u64| true);
+        let database_host = reader.value("database.host", "a host name or address", non_empty);
+        let database_port = reader.value("database.port", "a port between 1 and 65535", port);
+        let database_name = reader.value("database.name", "a database name", non_empty);
+        let database_user = reader.value("database.user", "a user name", non_empty);
+        let database_password = reader.value("database.password", "a password", |_: &String| true);
+        let pool_min = reader.value("database.pool_min", "a number of connections", |_: &usize| true);
+        let pool_max = reader.value("database.pool_max", "a positive number of connections", |max: &usize| *max > 0);
+        let pool_timeout = reader.value("database.pool_timeout_seconds", "a positive number of seconds", positive);
+        let connect_timeout = reader.value("database.connect_timeout_seconds", "a positive number of seconds", positive);
+        let read_timeout = reader.value("database.read_timeout_seconds", "a positive number of seconds", positive);
+        let connect_attempts = reader.value("database.connect_attempts", "a positive number", |attempts: &u32| *attempts > 0);
+        let tls_mode = reader.value("database.tls_mode", "one of disabled, required, verify_ca, verify_identity",
+            |_: &TlsMode| true);
+        let tls_ca = reader.optional("database.tls_ca", "a file path", non_empty);
+        if let (Some(min), Some(max)) = (pool_min, pool_max) {
+            if min > max {
+                reader.problems.push(format!("{}: `{min}` is more than {} `{max}`",
+                    describe("database.pool_min"), describe("database.pool_max")));
+            }
+        }
+        let log_level = reader.value("log.level", "a log filter, e.g. info",
+            |level: &String| non_empty(level) && EnvFilter::try_new(level).is_ok());
+        let ttl_seconds = reader.value("idempotency.ttl_seconds", "a positive number of seconds", positive);
+        let timezone = reader.value("restaurant.timezone", "an IANA timezone name, e.g. Europe/Berlin", |_: &Tz| true);
+        let otlp_endpoint = reader.optional("telemetry.otlp_endpoint", "an http(s) URL",
+            |endpoint: &String| endpoint.starts_with("http://") || endpoint.starts_with("https://"));
+        let service_name = reader.value("telemetry.service_name", "a service name", non_empty);
+
+        // Every `None` above recorded a problem.
+        let config = (|| Some(Config {
+            server: ServerConfig {
+                host: server_host?,
+                port: server_port?,
+                workers: workers?,
+                shutdown_delay_seconds: shutdown_delay?,
+                shutdown_timeout_seconds: shutdown_timeout?,
+            },
+            database: DatabaseConfig {
+                host: database_host?,
+                port: database_port?,
+                name: database_name?,
+                user: database_user?,
+                password: database_password?,
+                pool_min: pool_min?,
+                pool_max: pool_max?,
+                pool_timeout_seconds: pool_timeout?,
+                connect_timeout_seconds: connect_timeout?,
+                read_timeout_seconds: read_timeout?,
+                connect_attempts: connect_attempts?,
+                tls_mode: tls_mode?,
+                tls_ca: tls_ca?,
+            },
+            log: LogConfig { level: log_level? },
+            idempotency: IdempotencyConfig { ttl_seconds: ttl_seconds? },
+            restaurant: RestaurantConfig { timezone: timezone? },
+            telemetry: TelemetryConfig { otlp_endpoint: otlp_endpoint?, service_name: service_name? },
+        }))();
+        match config {
+            Some(config) if reader.problems.is_empty() => Ok(config),
+            _ => Err(ConfigError(reader.problems)),
+        }
+    }
+
+    /// The effective configuration as TOML, with secrets redacted.
+    pub fn redacted(&self) -> String {
+        toml::to_string(self).unwrap_or_default()
+    }
+}
+
+/// Reads typed values, collecting a problem instead of stopping at the first one.
+struct Reader<'a> {
+    /// Setting key to its raw value and where the value came from.
+    values: BTreeMap<&'static str, (String, &'a str)>,
+    problems: Vec<String>,
+}
+
+impl Reader<'_> {
+    fn value<T: FromStr>(&mut self, key: &str, expected: &str, is_valid: impl Fn(&T) -> bool) -> Option<T> {
+        let Some((value, source)) = self.values.get(key) else {
+            self.problems.push(format!("{} is required", describe(key)));
+            return None;
+        };
+        match value.parse() {
+            Ok(parsed) if is_valid(&parsed) => Some(parsed),
+            _ => {
+                self.problems.push(format!("{source}: `{}` is not {expected}", displayed(key, value)));
+                None
+            }
+        }
+    }
+
+    fn value_or<T: FromStr>(&mut self, key: &str, expected: &str, is_valid: impl Fn(&T) -> bool,
+                            default: impl FnOnce() -> T) -> Option<T> {
+        if self.values.contains_key(key) {
+            self.value(key, expected, is_valid)
+        } else {
+            Some(default())
+        }
+    }
+
+    /// `Some(None)` when the setting is absent, `None` when it is invalid.
+    fn optional<T: FromStr>(&mut self, key: &str, expected: &str, is_valid: impl Fn(&T) -> bool) -> Option<Option<T>> {
+        if self.values.contains_key(key) {
+            self.value(key, expected, is_valid).map(Some)
+        } else {
+            Some(None)
+        }
+    }
+}
+
+/// Flattens `[section] key = value` into `section.key`; unknown keys are reported, not ignored.
+fn read_file(path: &Path, problems: &mut Vec<String>) -> BTreeMap<String, String> {
+    let mut values = BTreeMap::new();
+    let table = match std::fs::read_to_string(path) {
+        Ok(content) => match content.parse::<toml::Table>() {
+            Ok(table) => table,
+            Err(e) => {
+                problems.push(format!("{}: {}", path.display(), e.message()));
+                return values;
+            }
+        },
+        Err(e) => {
+            problems.push(format!("{}: {e}", path.display()));
+            return values;
+        }
+    };
+
+    for (section, entries) in table {
+        let Some(entries) = entries.as_table() else {
+            problems.push(format!("{}: `{section}` must be a table", path.display()));
+            continue;
+        };
+        for (name, value) in entries {
+            let key = format!("{section}.{name}");
+            if !SETTINGS.iter().any(|setting| setting.key == key) {
+                problems.push(format!("{}: unknown setting `{key}`", path.display()));
+                continue;
+            }
+            match value {
+                toml::Value::String(value) => { values.insert(key, value.clone()); }
+                toml::Value::Integer(value) => { values.insert(key, value.to_string()); }
+                _ => problems.push(format!("{}: `{key}` must be a string or an integer", path.display())),
+            }
+        }
+    }
+    values
+}
+
+fn describe(key: &str) -> String {
+    SETTINGS.iter()
+        .find(|setting| setting.key == key)
+        .map(|setting| format!("{} ({})", setting.key, setting.env))
+        .unwrap_or_else(|| key.to_string())
+}
+
+fn displayed<'a>(key: &str, value: &'a str) -> &'a str {
+    if key == "database.password" { REDACTED } else { value }
+}
+
+fn default_workers() -> usize {
+    std::thread::available_parallelism().map(usize::from).unwrap_or(1)
+}
+
+fn redact<S: Serializer>(_: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(REDACTED)
+}
+
+fn timezone_name<S: Serializer>(timezone: &Tz, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(timezone.name())
+}
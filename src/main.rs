@@ -1,59 +1,176 @@
-use crate::api::restaurant_api::{add_items, get_item, get_all_items, get_table, remove_item};
+use crate::api::audit_api::get_audit_log;
+use crate::api::health_api;
+use crate::api::metrics_api;
+use crate::api::openapi::{get_openapi, ApiDoc};
+use crate::api::report_api::get_voided_items_report;
+use crate::api::restaurant_api::{add_items, bulk_update_status, bulk_void_items, get_item, get_all_items, get_table, remove_item, restore_item};
+use crate::api::restaurant_api_v2;
+use crate::cli::{run, Cli, Command};
+use crate::common::errors::{attach_request_id, extractor_error};
+use crate::common::metrics::{record_request_metrics, METRICS};
+use crate::common::shutdown::{stop_on_signal, Shutdown};
+use crate::common::telemetry::{self, trace_request};
+use crate::config::{Config, ServerConfig};
+use crate::common::routes::{register, Mount, Route};
+use crate::model::time::RestaurantTimezone;
+use crate::persist::connection::connect;
+use crate::persist::menu_items::get_menu_names;
+use crate::persist::migrations::{check_schema, SchemaState};
 use actix_request_identifier::RequestIdentifier;
-use actix_web::middleware::Logger;
+use actix_web::middleware::from_fn;
 use actix_web::{web, App, HttpServer};
-use log::info;
-use mysql::Pool;
-use std::env;
+use clap::Parser;
+use tracing::{error, info, warn};
+use std::process::exit;
+use std::time::Duration;
+use utoipa::OpenApi;
+use utoipa_scalar::{Scalar, Servable};
 
+mod cli;
 mod common;
+mod config;
 mod api;
 mod model;
 mod persist;
 
-const ENV_EXPECT_LOG:&str = "ENV var is NOT valid!";
-
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    info!("Initialize logger");
     dotenvy::dotenv().ok();
-    env_logger::init_from_env(env_logger::Env::new().default_filter_or(
-        env::var("LOG_LEVEL").expect(&format!("LOG_LEVEL {}", ENV_EXPECT_LOG))
-    ));
+    let cli = Cli::parse();
+    let config = Config::load(cli.config.as_deref()).unwrap_or_else(|e| {
+        eprint!("{e}");
+        exit(1);
+    });
+    if let Some(Command::Config) = cli.command {
+        print!("{}", config.redacted());
+        return Ok(());
+    }
+
+    let serving = matches!(cli.command, None | Some(Command::Serve));
+    let tracer_provider = telemetry::init(&config.log, serving.then_some(&config.telemetry)).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        exit(1);
+    });
 
     info!("Config DB and connection pools");
-    let db_host = env::var("MYSQL_HOST").expect(&format!("MYSQL_HOST {}", ENV_EXPECT_LOG));
-    let db_port:u16 = env::var("MYSQL_PORT").unwrap().parse().expect(&format!("MYSQL_PORT {}", ENV_EXPECT_LOG));
-    let db_name = env::var("MYSQL_DBNAME").expect(&format!("MYSQL_DBNAME {}", ENV_EXPECT_LOG));
-    let db_user = env::var("MYSQL_USER").expect(&format!("MYSQL_USER {}", ENV_EXPECT_LOG));
-    let db_passwd = env::var("MYSQL_PASSWORD").expect(&format!("MYSQL_PASSWORD {}", ENV_EXPECT_LOG));
-
-    let builder = mysql::OptsBuilder::new()
-        .ip_or_hostname(Some(db_host))
-        .tcp_port(db_port)
-        .db_name(Some(db_name))
-        .user(Some(db_user))
-        .pass(Some(db_passwd));
-
-    let pool = Pool::new(builder).unwrap();
+    let pool = connect(&config.database).unwrap_or_else(|e| {
+        error!("{e}");
+        exit(1);
+    });
+
+    let restaurant_timezone = RestaurantTimezone(config.restaurant.timezone);
+    match cli.command {
+        None | Some(Command::Serve) => {}
+        Some(command) => {
+            if let Err(e) = run(command, &pool, restaurant_timezone) {
+                error!("{e}");
+                exit(1);
+            }
+            return Ok(());
+        }
+    }
+    info!("Effective configuration:\n{}", config.redacted());
+
+    match check_schema(&pool) {
+        Ok(SchemaState::UpToDate) => {}
+        Ok(SchemaState::Ahead { current, expected }) =>
+            warn!("Schema is at version {current}, newer than the {expected} this binary knows"),
+        Ok(SchemaState::Outdated { current, expected }) => {
+            error!("Schema is at version {current}, expected {expected}; run `migrate up` first");
+            exit(1);
+        }
+        Ok(SchemaState::Uninitialized { expected }) => {
+            error!("No migration applied, expected version {expected}; run `migrate up` first");
+            exit(1);
+        }
+        Err(e) => {
+            error!("Can NOT check the schema version: {e}");
+            exit(1);
+        }
+    }
+    // Dishes seeded while serving are counted as "other" until the next start.
+    match get_menu_names(&pool) {
+        Ok(menu) => METRICS.set_menu(menu),
+        Err(e) => warn!("Can NOT read the menu, items are counted as \"other\": {e}"),
+    }
     let web_data = web::Data::new(pool);
-  
-    info!("Config server");
-    let workers = env::var("WORKERS").unwrap().parse().expect(&format!("WORKERS {}", ENV_EXPECT_LOG));
-    let host = env::var("HOST").expect(&format!("HOST {}", ENV_EXPECT_LOG));
-    let port:u16 = env::var("PORT").unwrap().parse().expect(&format!("PORT {}", ENV_EXPECT_LOG));
+    let idempotency_config = web::Data::new(config.idempotency);
+    let restaurant_timezone = web::Data::new(restaurant_timezone);
+    let ServerConfig { host, port, workers, shutdown_delay_seconds, shutdown_timeout_seconds } = config.server;
+    let shutdown = web::Data::new(Shutdown::new(
+        Duration::from_secs(shutdown_delay_seconds),
+        Duration::from_secs(shutdown_timeout_seconds),
+    ));
+    let pool = web_data.clone();
+    let shutdown_data = shutdown.clone();
 
-    HttpServer::new(move || {
+    let server = HttpServer::new(move || {
         App::new().app_data(web_data.clone())
-            .service(add_items)
-            .service(remove_item)
-            .service(get_table)
-            .service(get_all_items)
-            .service(get_item)
-            .wrap(Logger::default())
+            .app_data(idempotency_config.clone())
+            .app_data(restaurant_timezone.clone())
+            .app_data(shutdown_data.clone())
+            .app_data(web::JsonConfig::default().error_handler(|err, _| extractor_error("body", err)))
+            .app_data(web::QueryConfig::default().error_handler(|err, _| extractor_error("query", err)))
+            .app_data(web::PathConfig::default().error_handler(|err, _| extractor_error("path", err)))
+            .service(web::scope("/v1").app_data(Mount("/v1")).configure(v1_services))
+            .service(web::scope("/v2").app_data(Mount("/v2")).configure(v2_services))
+            // Unversioned paths predate /v1 and are kept for the deployed tablets.
+            .configure(v1_services)
+            .service(get_openapi)
+            .service(health_api::live)
+            .service(health_api::ready)
+            .service(metrics_api::metrics)
+            .service(Scalar::with_url("/docs", ApiDoc::openapi()))
+            .wrap(from_fn(attach_request_id))
+            .wrap(from_fn(record_request_metrics))
+            .wrap(from_fn(trace_request))
             .wrap(RequestIdentifier::with_uuid())
     }).bind((host, port))?
       .workers(workers)
-      .run()
-      .await
-}
\ No newline at end of file
+      .shutdown_timeout(shutdown_timeout_seconds)
+      .disable_signals()
+      .run();
+    actix_web::rt::spawn(stop_on_signal(server.handle(), shutdown.clone()));
+    // Consumes the server, so the app factory and its handle on the pool are gone once it returns.
+    let served = server.await;
+
+    if let Some(pool) = shutdown.release(pool).await {
+        drop(pool);
+        info!("Database pool closed");
+    }
+
+    // Exports the spans still batched.
+    if let Some(tracer_provider) = tracer_provider {
+        if let Err(e) = tracer_provider.shutdown() {
+            warn!("Can NOT flush the pending traces: {e}");
+        }
+    }
+    served
+}
+
+/// `/v1` is frozen: its request and response shapes must not change.
+fn v1_services(cfg: &mut web::ServiceConfig) {
+    register(cfg, Route::AddItems, add_items);
+    register(cfg, Route::VoidItem, remove_item);
+    register(cfg, Route::RestoreItem, restore_item);
+    register(cfg, Route::BulkVoidItems, bulk_void_items);
+    register(cfg, Route::BulkUpdateStatus, bulk_update_status);
+    register(cfg, Route::Table, get_table);
+    register(cfg, Route::TableItems, get_all_items);
+    register(cfg, Route::Item, get_item);
+    register(cfg, Route::AuditLog, get_audit_log);
+    register(cfg, Route::VoidedItemsReport, get_voided_items_report);
+}
+
+fn v2_services(cfg: &mut web::ServiceConfig) {
+    register(cfg, Route::AddItems, restaurant_api_v2::add_items);
+    register(cfg, Route::VoidItem, restaurant_api_v2::remove_item);
+    register(cfg, Route::RestoreItem, restaurant_api_v2::restore_item);
+    register(cfg, Route::BulkVoidItems, bulk_void_items);
+    register(cfg, Route::BulkUpdateStatus, bulk_update_status);
+    register(cfg, Route::Table, restaurant_api_v2::get_table);
+    register(cfg, Route::TableItems, restaurant_api_v2::get_all_items);
+    register(cfg, Route::Item, restaurant_api_v2::get_item);
+    register(cfg, Route::AuditLog, restaurant_api_v2::get_audit_log);
+    register(cfg, Route::VoidedItemsReport, restaurant_api_v2::get_voided_items_report);
+}
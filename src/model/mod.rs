@@ -1,3 +1,7 @@
+pub mod pagination;
 pub mod resources;
 pub mod requests;
-pub mod responses;
\ No newline at end of file
+pub mod responses;
+pub mod time;
+pub mod v2;
+pub mod validation;
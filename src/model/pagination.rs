@@ -0,0 +1,150 @@
+use serde_json::{json, Value};
+
+use crate::common::errors::{AppError, FieldError};
+use crate::model::requests::TableItemsQuery;
+use crate::model::resources::TableItem;
+
+pub const DEFAULT_PAGE_LIMIT: u32 = 50;
+pub const MAX_PAGE_LIMIT: u32 = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortField {
+    ItemId,
+    ItemName,
+    OrderedOn,
+    PrepareMinutes,
+}
+
+impl SortField {
+    pub fn column(&self) -> &'static str {
+        match self {
+            SortField::ItemId => "item_id",
+            SortField::ItemName => "item_name",
+            SortField::OrderedOn => "ordered_on",
+            SortField::PrepareMinutes => "prepare_minutes",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "item_id" => Some(SortField::ItemId),
+            "item_name" => Some(SortField::ItemName),
+            "ordered_on" => Some(SortField::OrderedOn),
+            "prepare_minutes" => Some(SortField::PrepareMinutes),
+            _ => None,
+        }
+    }
+
+    fn value_of(&self, item: &TableItem) -> Value {
+        match self {
+            SortField::ItemId => json!(item.item_id),
+            SortField::ItemName => json!(item.item_name),
+            SortField::OrderedOn => json!(item.ordered_on),
+            SortField::PrepareMinutes => json!(item.prepare_minutes),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Sort {
+    pub field: SortField,
+    pub descending: bool,
+}
+
+impl Default for Sort {
+    fn default() -> Self {
+        Sort { field: SortField::ItemId, descending: false }
+    }
+}
+
+impl Sort {
+    fn parse(value: &str) -> Option<Self> {
+        match value.strip_prefix('-') {
+            Some(field) => SortField::parse(field).map(|field| Sort { field, descending: true }),
+            None => SortField::parse(value).map(|field| Sort { field, descending: false }),
+        }
+    }
+}
+
+/// Position of a row in the sort order: its sort value, with `item_id` as tie-breaker.
+/// Encoded as hex JSON so clients treat it as an opaque token.
+#[derive(Debug, Clone)]
+pub struct Cursor {
+    pub value: Value,
+    pub item_id: u32,
+}
+
+impl Cursor {
+    pub fn of(item: &TableItem, field: SortField) -> Self {
+        Cursor { value: field.value_of(item), item_id: item.item_id }
+    }
+
+    pub fn encode(&self) -> String {
+        json!([self.value, self.item_id]).to_string()
+            .bytes()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    fn decode(token: &str) -> Option<Self> {
+        let bytes = (0..token.len()).step_by(2)
+            .map(|i| token.get(i..i + 2).and_then(|pair| u8::from_str_radix(pair, 16).ok()))
+            .collect::<Option<Vec<u8>>>()?;
+        let (value, item_id): (Value, u32) = serde_json::from_slice(&bytes).ok()?;
+        Some(Cursor { value, item_id })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PageRequest {
+    pub sort: Sort,
+    pub after: Option<Cursor>,
+    pub before: Option<Cursor>,
+    pub limit: u32,
+}
+
+impl PageRequest {
+    /// Paging backwards (`before`) walks the sort order in reverse.
+    pub fn is_backward(&self) -> bool {
+        self.before.is_some()
+    }
+
+    pub fn from_query(query: &TableItemsQuery) -> Result<Self, AppError> {
+        let mut errors = Vec::new();
+
+        let sort = match query.sort.as_deref() {
+            Some(sort) => Sort::parse(sort).unwrap_or_else(|| {
+                errors.push(FieldError::new(
+                    "sort",
+                    "must be one of item_id, item_name, ordered_on, prepare_minutes, optionally prefixed with '-'",
+                ));
+                Sort::default()
+            }),
+            None => Sort::default(),
+        };
+        let after = decode_cursor("after", query.after.as_deref(), &mut errors);
+        let before = decode_cursor("before", query.before.as_deref(), &mut errors);
+        if after.is_some() && before.is_some() {
+            errors.push(FieldError::new("before", "can not be combined with after"));
+        }
+        let limit = query.limit.unwrap_or(DEFAULT_PAGE_LIMIT);
+        if limit == 0 || limit > MAX_PAGE_LIMIT {
+            errors.push(FieldError::new("limit", format!("must be between 1 and {MAX_PAGE_LIMIT}")));
+        }
+
+        if errors.is_empty() {
+            Ok(PageRequest { sort, after, before, limit })
+        } else {
+            Err(AppError::Validation(errors))
+        }
+    }
+}
+
+fn decode_cursor(field: &str, token: Option<&str>, errors: &mut Vec<FieldError>) -> Option<Cursor> {
+    let token = token?;
+    let cursor = Cursor::decode(token);
+    if cursor.is_none() {
+        errors.push(FieldError::new(field, "is not a valid page cursor"));
+    }
+    cursor
+}
@@ -1,6 +1,167 @@
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::common::errors::{AppError, FieldError};
+use crate::model::resources::{AuditAction, ItemStatus, VoidReason};
+use crate::model::time::TimeBound;
+
+const MAX_FILTER_VALUES: usize = 100;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct AddItemsToTableRequest {
     pub items_names: Vec<String>,
 }
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditQuery {
+    pub table_number: Option<u32>,
+    pub item_id: Option<u32>,
+    pub action: Option<AuditAction>,
+    pub actor: Option<String>,
+    pub request_id: Option<String>,
+    /// RFC 3339, or the restaurant's local time when given without an offset.
+    #[param(value_type = Option<String>)]
+    pub from: Option<TimeBound>,
+    #[param(value_type = Option<String>)]
+    pub to: Option<TimeBound>,
+    pub limit: Option<u32>,
+}
+
+/// How a bulk operation treats items that can not be changed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkMode {
+    /// Any failing item rolls the whole batch back.
+    #[default]
+    AllOrNothing,
+    /// Failing items are reported and skipped, the others are committed.
+    BestEffort,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BulkItemRef {
+    pub item_id: u32,
+    /// Expected item version, as in `If-Match`; any version is accepted when omitted.
+    pub version: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BulkVoidItemsRequest {
+    pub items: Vec<BulkItemRef>,
+    pub reason: VoidReason,
+    #[serde(default)]
+    pub mode: BulkMode,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BulkUpdateStatusRequest {
+    pub items: Vec<BulkItemRef>,
+    pub status: ItemStatus,
+    #[serde(default)]
+    pub mode: BulkMode,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct VoidItemQuery {
+    pub reason: VoidReason,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct VoidReportQuery {
+    pub table_number: Option<u32>,
+    pub reason: Option<VoidReason>,
+    /// RFC 3339, or the restaurant's local time when given without an offset.
+    #[param(value_type = Option<String>)]
+    pub from: Option<TimeBound>,
+    #[param(value_type = Option<String>)]
+    pub to: Option<TimeBound>,
+}
+
+/// Query of `GET /tables/{n}/items`. `name` and `id` may be repeated (`?name=Pizza&name=Soup`), which
+/// `serde_urlencoded` can not map onto a struct, so they are parsed by [`TableItemsQuery::from_query_string`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TableItemsQuery {
+    #[serde(skip)]
+    pub name: Vec<String>,
+    #[serde(skip)]
+    pub id: Vec<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name_contains: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<ItemStatus>,
+    /// RFC 3339, or the restaurant's local time when given without an offset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[param(value_type = Option<String>)]
+    pub ordered_from: Option<TimeBound>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[param(value_type = Option<String>)]
+    pub ordered_to: Option<TimeBound>,
+    /// Only items past (`true`) or not past (`false`) their ready time and not served yet.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overdue: Option<bool>,
+    /// Field to sort by, prefixed with `-` for descending order, e.g. `-ordered_on`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+}
+
+impl TableItemsQuery {
+    pub fn from_query_string(query_string: &str) -> Result<Self, AppError> {
+        let pairs: Vec<(String, String)> = serde_urlencoded::from_str(query_string)
+            .map_err(|e| AppError::Validation(vec![FieldError::new("query", e.to_string())]))?;
+        let (repeated, single): (Vec<_>, Vec<_>) = pairs.into_iter()
+            .partition(|(key, _)| key == "name" || key == "id");
+
+        let single = serde_urlencoded::to_string(&single).unwrap_or_default();
+        let mut query: TableItemsQuery = serde_urlencoded::from_str(&single)
+            .map_err(|e| AppError::Validation(vec![FieldError::new("query", e.to_string())]))?;
+
+        let mut errors = Vec::new();
+        let mut id_index = 0;
+        for (key, value) in repeated {
+            if key == "name" {
+                query.name.push(value);
+                continue;
+            }
+            match value.parse() {
+                Ok(id) => query.id.push(id),
+                Err(_) => errors.push(FieldError::new(format!("id[{id_index}]"), "must be a positive integer")),
+            }
+            id_index += 1;
+        }
+        if query.name.len() > MAX_FILTER_VALUES {
+            errors.push(FieldError::new("name", format!("must be repeated at most {MAX_FILTER_VALUES} times")));
+        }
+        if query.id.len() > MAX_FILTER_VALUES {
+            errors.push(FieldError::new("id", format!("must be repeated at most {MAX_FILTER_VALUES} times")));
+        }
+
+        if errors.is_empty() {
+            Ok(query)
+        } else {
+            Err(AppError::Validation(errors))
+        }
+    }
+
+    pub fn to_query_string(&self) -> String {
+        let repeated: Vec<(&str, String)> = self.name.iter()
+            .map(|name| ("name", name.clone()))
+            .chain(self.id.iter().map(|id| ("id", id.to_string())))
+            .collect();
+        let repeated = serde_urlencoded::to_string(&repeated).unwrap_or_default();
+        let single = serde_urlencoded::to_string(self).unwrap_or_default();
+        [repeated, single].into_iter()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+}
@@ -1,75 +1,329 @@
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
-use hateoas::{HttpMethod, RelLink, RelLinkCollection};
+use hateoas::RelLinkCollection;
+use utoipa::ToSchema;
 
-#[derive(Serialize, Deserialize)]
+use crate::common::representation::{Hypermedia, Kind};
+use crate::common::routes::{Links, Route};
+use crate::model::time::RestaurantTimezone;
+
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
 pub struct TableItem {
     pub item_id: u32,
     pub table_number: u32,
     pub item_name: String,
-    pub ordered_on: String,
+    pub ordered_on: DateTime<Utc>,
     pub prepare_minutes: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voided_on: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub void_reason: Option<VoidReason>,
+    pub version: u32,
+    pub status: ItemStatus,
+}
+
+impl TableItem {
+    /// When the kitchen should be done with the item: `ordered_on` plus `prepare_minutes`.
+    pub fn ready_at(&self) -> DateTime<Utc> {
+        self.ordered_on + Duration::minutes(self.prepare_minutes.into())
+    }
+
+    /// Served and voided items are no longer waited for.
+    fn is_pending(&self) -> bool {
+        self.status != ItemStatus::Served && self.voided_on.is_none()
+    }
+
+    /// Whole minutes until `ready_at`, rounded up; 0 once it passed or the item is no longer pending.
+    pub fn remaining_minutes(&self, now: DateTime<Utc>) -> u32 {
+        if !self.is_pending() {
+            return 0;
+        }
+        let remaining_seconds = (self.ready_at() - now).num_seconds().max(0);
+        u32::try_from((remaining_seconds + 59) / 60).unwrap_or(u32::MAX)
+    }
+
+    pub fn is_overdue(&self, now: DateTime<Utc>) -> bool {
+        self.is_pending() && self.ready_at() < now
+    }
 }
 
+/// Kitchen progress of an item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemStatus {
+    Ordered,
+    Preparing,
+    Served,
+}
+
+impl ItemStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ItemStatus::Ordered => "ordered",
+            ItemStatus::Preparing => "preparing",
+            ItemStatus::Served => "served",
+        }
+    }
 
-#[derive(Serialize, Deserialize)]
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "ordered" => Some(ItemStatus::Ordered),
+            "preparing" => Some(ItemStatus::Preparing),
+            "served" => Some(ItemStatus::Served),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VoidReason {
+    GuestChangedMind,
+    KitchenError,
+    Comp,
+}
+
+impl VoidReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VoidReason::GuestChangedMind => "guest_changed_mind",
+            VoidReason::KitchenError => "kitchen_error",
+            VoidReason::Comp => "comp",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "guest_changed_mind" => Some(VoidReason::GuestChangedMind),
+            "kitchen_error" => Some(VoidReason::KitchenError),
+            "comp" => Some(VoidReason::Comp),
+            _ => None,
+        }
+    }
+}
+
+/// Preparation time of a dish; items added under its name take it instead of a random one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MenuItem {
+    pub item_name: String,
+    pub prepare_minutes: u32,
+}
+
+/// Menu file read by `seed-menu`, a TOML list of `[[items]]`.
+#[derive(Debug, Deserialize)]
+pub struct Menu {
+    pub items: Vec<MenuItem>,
+}
+
+
+/// Documents the serialized shape of a `hateoas` link.
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub struct Link {
+    href: String,
+    rel: String,
+    method: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct TableItemResource {
     pub item_id: u32,
     pub table_number: u32,
     pub item_name: String,
     pub ordered_on: String,
     pub prepare_minutes: u32,
+    /// `ordered_on` plus `prepare_minutes`, in the same format.
+    pub ready_at: String,
+    /// Computed against the server's clock when the response is rendered.
+    pub remaining_minutes: u32,
+    /// Not served yet although `ready_at` passed.
+    pub overdue: bool,
+    pub status: ItemStatus,
+    pub version: u32,
+    #[schema(value_type = Vec<Link>)]
     pub _links: RelLinkCollection,
 }
 
 impl TableItemResource {
-    pub fn new(item_id: u32, table_number: u32, item_name: String, ordered_on: String, prepare_minutes: u32) -> Self {
-        let mut resource = Self {
-            item_id, table_number, item_name, ordered_on, prepare_minutes,
-            _links: RelLinkCollection::new(vec![])
-        };
-        let self_link = format!("/tables/{}/items/{}", table_number, item_id);
-        resource.add_link("self", &self_link, HttpMethod::Get);
-        resource.add_link("delete", &self_link, HttpMethod::Delete);
-
-        let table_link = format!("/tables/{}", table_number);
-        resource.add_link("table", &table_link, HttpMethod::Get);
-
-        resource
+    pub fn new(links: &Links, timezone: &RestaurantTimezone, item: TableItem) -> Self {
+        let params = [item.table_number, item.item_id];
+        let now = Utc::now();
+        Self {
+            item_id: item.item_id,
+            table_number: item.table_number,
+            ordered_on: timezone.v1(item.ordered_on),
+            ready_at: timezone.v1(item.ready_at()),
+            remaining_minutes: item.remaining_minutes(now),
+            overdue: item.is_overdue(now),
+            item_name: item.item_name,
+            prepare_minutes: item.prepare_minutes,
+            status: item.status,
+            version: item.version,
+            _links: RelLinkCollection::new(vec![
+                links.link("self", Route::Item, &params),
+                links.link("delete", Route::VoidItem, &params),
+                links.link("table", Route::Table, &params[..1]),
+            ]),
+        }
     }
+}
 
-    fn add_link(&mut self, rel: &str, href: &str, method: HttpMethod) {
-        self._links.add(
-            RelLink::new(rel, href, method)
-        );
-    }
+impl Hypermedia for TableItemResource {
+    const KIND: Option<Kind> = Some(Kind { name: "items", id_field: "item_id" });
 }
 
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct TableResource {
     pub table_number: u32,
     pub items: Vec<TableItemResource>,
+    #[schema(value_type = Vec<Link>)]
     pub _links: RelLinkCollection,
 }
 
 impl TableResource {
-    pub(crate) fn new(table_number: u32, items: Vec<TableItemResource>) -> Self {
-        let mut resource = Self {
+    pub(crate) fn new(links: &Links, table_number: u32, items: Vec<TableItemResource>) -> Self {
+        Self {
             table_number,
             items,
-            _links: RelLinkCollection::new(vec![]),
-        };
-        let self_link = format!("/tables/{table_number}");
-        resource.add_link("self", self_link.as_str(), HttpMethod::Get);
+            _links: RelLinkCollection::new(vec![
+                links.link("self", Route::Table, &[table_number]),
+                links.link("add_items", Route::AddItems, &[table_number]),
+            ]),
+        }
+    }
+}
 
-        let add_item_link = format!("/tables/{table_number}");
-        resource.add_link("add_items", add_item_link.as_str(), HttpMethod::Put);
+impl Hypermedia for TableResource {
+    const KIND: Option<Kind> = Some(Kind { name: "tables", id_field: "table_number" });
+}
 
-        resource
+pub(crate) fn page_links(links: &Links, table_number: u32, self_query: &str,
+                         next_query: Option<String>, prev_query: Option<String>) -> RelLinkCollection {
+    let params = [table_number];
+    let mut page_links = RelLinkCollection::new(vec![
+        links.link_with_query("self", Route::TableItems, &params, self_query),
+    ]);
+    if let Some(next_query) = next_query {
+        page_links.add(links.link_with_query("next", Route::TableItems, &params, &next_query));
     }
-    fn add_link(&mut self, rel: &str, href: &str, method: HttpMethod) {
-        self._links.add(
-            RelLink::new(rel, href, method)
-        );
+    if let Some(prev_query) = prev_query {
+        page_links.add(links.link_with_query("prev", Route::TableItems, &params, &prev_query));
+    }
+    page_links.add(links.link("table", Route::Table, &params));
+    page_links
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Add,
+    Void,
+    Restore,
+    Update,
+    /// Items deleted by `items purge`; one entry per run, see `record_purge_entry`.
+    Purge,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Add => "add",
+            AuditAction::Void => "void",
+            AuditAction::Restore => "restore",
+            AuditAction::Update => "update",
+            AuditAction::Purge => "purge",
+        }
     }
-}
\ No newline at end of file
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct AuditEntry {
+    pub audit_id: u64,
+    pub action: String,
+    pub actor: String,
+    pub request_id: String,
+    pub table_number: u32,
+    pub item_id: u32,
+    #[schema(value_type = Option<TableItem>)]
+    pub before: Option<serde_json::Value>,
+    #[schema(value_type = Option<TableItem>)]
+    pub after: Option<serde_json::Value>,
+    pub recorded_on: DateTime<Utc>,
+}
+
+/// `/v1` rendering of an audit entry: `recorded_on` and the snapshots' timestamps in the restaurant's local time.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct AuditEntryResource {
+    pub audit_id: u64,
+    pub action: String,
+    pub actor: String,
+    pub request_id: String,
+    pub table_number: u32,
+    pub item_id: u32,
+    #[schema(value_type = Option<VoidedItemResource>)]
+    pub before: Option<serde_json::Value>,
+    #[schema(value_type = Option<VoidedItemResource>)]
+    pub after: Option<serde_json::Value>,
+    pub recorded_on: NaiveDateTime,
+}
+
+impl AuditEntryResource {
+    pub fn new(timezone: &RestaurantTimezone, entry: AuditEntry) -> Self {
+        Self {
+            audit_id: entry.audit_id,
+            action: entry.action,
+            actor: entry.actor,
+            request_id: entry.request_id,
+            table_number: entry.table_number,
+            item_id: entry.item_id,
+            before: entry.before.map(|before| v1_snapshot(timezone, before)),
+            after: entry.after.map(|after| v1_snapshot(timezone, after)),
+            recorded_on: timezone.local(entry.recorded_on).naive_local(),
+        }
+    }
+}
+
+/// Snapshots are stored with RFC 3339 timestamps; those written before timestamps were stored
+/// in UTC already have the `/v1` format and are kept as they are.
+fn v1_snapshot(timezone: &RestaurantTimezone, mut snapshot: serde_json::Value) -> serde_json::Value {
+    for field in ["ordered_on", "voided_on"] {
+        let Some(value) = snapshot.get_mut(field) else { continue };
+        if let Some(at) = value.as_str().and_then(|at| DateTime::parse_from_rfc3339(at).ok()) {
+            *value = timezone.v1(at.with_timezone(&Utc)).into();
+        }
+    }
+    snapshot
+}
+
+/// `/v1` rendering of a voided item in the report.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct VoidedItemResource {
+    pub item_id: u32,
+    pub table_number: u32,
+    pub item_name: String,
+    pub ordered_on: String,
+    pub prepare_minutes: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voided_on: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub void_reason: Option<VoidReason>,
+    pub version: u32,
+    pub status: ItemStatus,
+}
+
+impl VoidedItemResource {
+    pub fn new(timezone: &RestaurantTimezone, item: TableItem) -> Self {
+        Self {
+            item_id: item.item_id,
+            table_number: item.table_number,
+            item_name: item.item_name,
+            ordered_on: timezone.v1(item.ordered_on),
+            prepare_minutes: item.prepare_minutes,
+            voided_on: item.voided_on.map(|voided_on| timezone.v1(voided_on)),
+            void_reason: item.void_reason,
+            version: item.version,
+            status: item.status,
+        }
+    }
+}
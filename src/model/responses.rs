@@ -1,14 +1,77 @@
+use std::collections::{BTreeMap, HashMap};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Serialize, Deserialize)]
+use crate::model::requests::BulkMode;
+use crate::model::resources::{VoidReason, VoidedItemResource};
+
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct AddItemsResponse {
     pub status: String,
     pub message: String,
     pub items_ids: Vec<u32>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct RemoveTableItemResponse {
     pub status: String,
     pub message: String
-}
\ No newline at end of file
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct VoidReportResponse {
+    pub total: usize,
+    #[schema(value_type = HashMap<String, usize>)]
+    pub by_reason: HashMap<VoidReason, usize>,
+    pub items: Vec<VoidedItemResource>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkItemOutcome {
+    Applied,
+    NotFound,
+    Conflict,
+    VersionMismatch,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct BulkItemResult {
+    pub item_id: u32,
+    pub outcome: BulkItemOutcome,
+    /// New version of the item when the change was applied.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct BulkOperationResponse {
+    pub mode: BulkMode,
+    /// `false` when an all-or-nothing batch was rolled back.
+    pub committed: bool,
+    pub results: Vec<BulkItemResult>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Up,
+    Down,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DependencyCheck {
+    pub status: HealthStatus,
+    /// Time the check took, in milliseconds.
+    pub latency_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct HealthResponse {
+    /// `up` when every check is.
+    pub status: HealthStatus,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub checks: BTreeMap<String, DependencyCheck>,
+}
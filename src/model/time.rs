@@ -0,0 +1,75 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use chrono::{DateTime, Duration, FixedOffset, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Legacy `/v1` rendering of item timestamps, in the restaurant's timezone.
+pub const V1_TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Timestamps are stored in UTC; the restaurant's timezone is used to show them and to read
+/// report and filter bounds given without an offset.
+#[derive(Debug, Clone, Copy)]
+pub struct RestaurantTimezone(pub Tz);
+
+impl RestaurantTimezone {
+    pub fn local(&self, at: DateTime<Utc>) -> DateTime<FixedOffset> {
+        at.with_timezone(&self.0).fixed_offset()
+    }
+
+    /// `at` in the `/v1` format.
+    pub fn v1(&self, at: DateTime<Utc>) -> String {
+        self.local(at).format(V1_TIMESTAMP_FORMAT).to_string()
+    }
+
+    pub fn resolve(&self, bound: &TimeBound) -> DateTime<Utc> {
+        match bound {
+            TimeBound::Absolute(at) => at.with_timezone(&Utc),
+            // A local time skipped by a DST change does not exist; the one an hour later does.
+            TimeBound::Local(at) => self.0.from_local_datetime(at).earliest()
+                .or_else(|| self.0.from_local_datetime(&(*at + Duration::hours(1))).earliest())
+                .map(|at| at.with_timezone(&Utc))
+                .unwrap_or_else(|| Utc.from_utc_datetime(at)),
+        }
+    }
+}
+
+/// Bound of a time range in a query: RFC 3339 with an offset (`2024-12-02T04:34:35+01:00`),
+/// or a local time of the restaurant (`2024-12-02T04:34:35`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimeBound {
+    Absolute(DateTime<FixedOffset>),
+    Local(NaiveDateTime),
+}
+
+impl FromStr for TimeBound {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        DateTime::parse_from_rfc3339(value).map(TimeBound::Absolute)
+            .or_else(|_| NaiveDateTime::from_str(value).map(TimeBound::Local))
+            .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f").map(TimeBound::Local))
+            .map_err(|_| format!("invalid timestamp `{value}`, expected RFC 3339 or YYYY-MM-DDTHH:MM:SS"))
+    }
+}
+
+impl Display for TimeBound {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimeBound::Absolute(at) => write!(f, "{}", at.to_rfc3339()),
+            TimeBound::Local(at) => write!(f, "{}", at.format("%Y-%m-%dT%H:%M:%S%.f")),
+        }
+    }
+}
+
+impl Serialize for TimeBound {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for TimeBound {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
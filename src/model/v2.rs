@@ -0,0 +1,153 @@
+//! Request and resource shapes of the `/v2` API. They carry the same data as the `/v1` ones,
+//! which stay frozen for deployed clients, and are built from the same persistence types.
+use std::collections::HashMap;
+use chrono::{DateTime, FixedOffset, Utc};
+use hateoas::RelLinkCollection;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::common::representation::{Hypermedia, Kind};
+use crate::common::routes::{Links, Route};
+use crate::model::resources::{page_links, ItemStatus, Link, TableItem, VoidReason};
+use crate::model::time::RestaurantTimezone;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = v2::NewItem)]
+pub struct NewItem {
+    pub item_name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = v2::AddItemsRequest)]
+pub struct AddItemsRequest {
+    pub items: Vec<NewItem>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[schema(as = v2::AddedItem)]
+pub struct AddedItem {
+    pub item_id: u32,
+    pub item_name: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[schema(as = v2::AddItemsResponse)]
+pub struct AddItemsResponse {
+    pub items: Vec<AddedItem>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[schema(as = v2::ItemResource)]
+pub struct ItemResource {
+    pub item_id: u32,
+    pub table_number: u32,
+    pub item_name: String,
+    /// RFC 3339, with the offset of the restaurant's timezone.
+    pub ordered_on: DateTime<FixedOffset>,
+    pub prepare_minutes: u32,
+    /// `ordered_on` plus `prepare_minutes`.
+    pub ready_at: DateTime<FixedOffset>,
+    /// Computed against the server's clock when the response is rendered.
+    pub remaining_minutes: u32,
+    /// Not served yet although `ready_at` passed.
+    pub overdue: bool,
+    pub status: ItemStatus,
+    pub version: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voided_on: Option<DateTime<FixedOffset>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub void_reason: Option<VoidReason>,
+    #[schema(value_type = Vec<Link>)]
+    pub _links: RelLinkCollection,
+}
+
+impl ItemResource {
+    pub fn new(links: &Links, timezone: &RestaurantTimezone, item: TableItem) -> Self {
+        let params = [item.table_number, item.item_id];
+        let now = Utc::now();
+        Self {
+            item_id: item.item_id,
+            table_number: item.table_number,
+            ordered_on: timezone.local(item.ordered_on),
+            ready_at: timezone.local(item.ready_at()),
+            remaining_minutes: item.remaining_minutes(now),
+            overdue: item.is_overdue(now),
+            item_name: item.item_name,
+            prepare_minutes: item.prepare_minutes,
+            status: item.status,
+            version: item.version,
+            voided_on: item.voided_on.map(|voided_on| timezone.local(voided_on)),
+            void_reason: item.void_reason,
+            _links: RelLinkCollection::new(vec![
+                links.link("self", Route::Item, &params),
+                links.link("delete", Route::VoidItem, &params),
+                links.link("table", Route::Table, &params[..1]),
+            ]),
+        }
+    }
+}
+
+impl Hypermedia for ItemResource {
+    const KIND: Option<Kind> = Some(Kind { name: "items", id_field: "item_id" });
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[schema(as = v2::ItemsPageResource)]
+pub struct ItemsPageResource {
+    pub items: Vec<ItemResource>,
+    #[schema(value_type = Vec<Link>)]
+    pub _links: RelLinkCollection,
+}
+
+impl ItemsPageResource {
+    /// Query strings are the ones of the current, next and previous pages, without the leading `?`.
+    pub fn new(links: &Links, table_number: u32, items: Vec<ItemResource>, self_query: &str,
+               next_query: Option<String>, prev_query: Option<String>) -> Self {
+        Self {
+            items,
+            _links: page_links(links, table_number, self_query, next_query, prev_query),
+        }
+    }
+}
+
+impl Hypermedia for ItemsPageResource {
+    const KIND: Option<Kind> = None;
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[schema(as = v2::TableResource)]
+pub struct TableResource {
+    pub table_number: u32,
+    pub items: Vec<ItemResource>,
+    #[schema(value_type = Vec<Link>)]
+    pub _links: RelLinkCollection,
+}
+
+impl TableResource {
+    pub fn new(links: &Links, table_number: u32, items: Vec<ItemResource>) -> Self {
+        let params = [table_number];
+        Self {
+            table_number,
+            items,
+            _links: RelLinkCollection::new(vec![
+                links.link("self", Route::Table, &params),
+                links.link("items", Route::TableItems, &params),
+                links.link("add_items", Route::AddItems, &params),
+            ]),
+        }
+    }
+}
+
+impl Hypermedia for TableResource {
+    const KIND: Option<Kind> = Some(Kind { name: "tables", id_field: "table_number" });
+}
+
+/// Voided items with RFC 3339 timestamps in UTC.
+#[derive(Serialize, Deserialize, ToSchema)]
+#[schema(as = v2::VoidReportResponse)]
+pub struct VoidReportResponse {
+    pub total: usize,
+    #[schema(value_type = HashMap<String, usize>)]
+    pub by_reason: HashMap<VoidReason, usize>,
+    pub items: Vec<TableItem>,
+}
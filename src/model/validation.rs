@@ -0,0 +1,172 @@
+use std::collections::HashSet;
+
+use crate::common::errors::{AppError, FieldError};
+use crate::model::requests::{AddItemsToTableRequest, BulkItemRef, BulkUpdateStatusRequest, BulkVoidItemsRequest};
+use crate::model::resources::Menu;
+use crate::model::v2;
+
+/// Matches `table_items.item_name VARCHAR(127)`, which counts characters, not bytes.
+pub const MAX_ITEM_NAME_LENGTH: usize = 127;
+pub const MAX_ITEMS_PER_REQUEST: usize = 50;
+pub const MAX_ITEMS_PER_BULK_REQUEST: usize = 100;
+pub const MAX_PREPARE_MINUTES: u32 = 240;
+/// Matches `idempotency_keys.idempotency_key VARCHAR(255)`.
+pub const MAX_IDEMPOTENCY_KEY_LENGTH: usize = 255;
+
+pub trait Validate {
+    fn validate(&self) -> Result<(), AppError>;
+}
+
+impl Validate for AddItemsToTableRequest {
+    fn validate(&self) -> Result<(), AppError> {
+        let mut errors = Vec::new();
+
+        if self.items_names.is_empty() {
+            errors.push(FieldError::new("items_names", "must contain at least one item"));
+        }
+        if self.items_names.len() > MAX_ITEMS_PER_REQUEST {
+            errors.push(FieldError::new(
+                "items_names",
+                format!("must contain at most {MAX_ITEMS_PER_REQUEST} items, got {}", self.items_names.len()),
+            ));
+        }
+        for (index, item_name) in self.items_names.iter().enumerate() {
+            if let Some(message) = validate_item_name(item_name) {
+                errors.push(FieldError::new(format!("items_names[{index}]"), message));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::Validation(errors))
+        }
+    }
+}
+
+impl Validate for v2::AddItemsRequest {
+    fn validate(&self) -> Result<(), AppError> {
+        let mut errors = Vec::new();
+
+        if self.items.is_empty() {
+            errors.push(FieldError::new("items", "must contain at least one item"));
+        }
+        if self.items.len() > MAX_ITEMS_PER_REQUEST {
+            errors.push(FieldError::new(
+                "items",
+                format!("must contain at most {MAX_ITEMS_PER_REQUEST} items, got {}", self.items.len()),
+            ));
+        }
+        for (index, item) in self.items.iter().enumerate() {
+            if let Some(message) = validate_item_name(&item.item_name) {
+                errors.push(FieldError::new(format!("items[{index}].item_name"), message));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::Validation(errors))
+        }
+    }
+}
+
+impl Validate for BulkVoidItemsRequest {
+    fn validate(&self) -> Result<(), AppError> {
+        validate_bulk_items(&self.items)
+    }
+}
+
+impl Validate for BulkUpdateStatusRequest {
+    fn validate(&self) -> Result<(), AppError> {
+        validate_bulk_items(&self.items)
+    }
+}
+
+impl Validate for Menu {
+    fn validate(&self) -> Result<(), AppError> {
+        let mut errors = Vec::new();
+
+        if self.items.is_empty() {
+            errors.push(FieldError::new("items", "must contain at least one item"));
+        }
+        let mut seen = HashSet::new();
+        for (index, item) in self.items.iter().enumerate() {
+            if let Some(message) = validate_item_name(&item.item_name) {
+                errors.push(FieldError::new(format!("items[{index}].item_name"), message));
+            } else if !seen.insert(item.item_name.to_lowercase()) {
+                errors.push(FieldError::new(format!("items[{index}].item_name"), format!("duplicates {}", item.item_name)));
+            }
+            if item.prepare_minutes == 0 || item.prepare_minutes > MAX_PREPARE_MINUTES {
+                errors.push(FieldError::new(
+                    format!("items[{index}].prepare_minutes"),
+                    format!("must be between 1 and {MAX_PREPARE_MINUTES}"),
+                ));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::Validation(errors))
+        }
+    }
+}
+
+fn validate_bulk_items(items: &[BulkItemRef]) -> Result<(), AppError> {
+    let mut errors = Vec::new();
+
+    if items.is_empty() {
+        errors.push(FieldError::new("items", "must contain at least one item"));
+    }
+    if items.len() > MAX_ITEMS_PER_BULK_REQUEST {
+        errors.push(FieldError::new(
+            "items",
+            format!("must contain at most {MAX_ITEMS_PER_BULK_REQUEST} items, got {}", items.len()),
+        ));
+    }
+    let mut seen = HashSet::new();
+    for (index, item) in items.iter().enumerate() {
+        if !seen.insert(item.item_id) {
+            errors.push(FieldError::new(format!("items[{index}].item_id"), format!("duplicates item {}", item.item_id)));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::Validation(errors))
+    }
+}
+
+fn validate_item_name(item_name: &str) -> Option<String> {
+    if item_name.trim().is_empty() {
+        return Some("must not be blank".into());
+    }
+    let length = item_name.chars().count();
+    if length > MAX_ITEM_NAME_LENGTH {
+        return Some(format!("must be at most {MAX_ITEM_NAME_LENGTH} characters, got {length}"));
+    }
+    if item_name.chars().any(char::is_control) {
+        return Some("must not contain control characters".into());
+    }
+    None
+}
+
+/// Accepts 1 to 255 visible ASCII characters, e.g. a UUID generated by the client.
+pub fn validate_idempotency_key(header_name: &str, value: &[u8]) -> Result<String, AppError> {
+    let message = if value.is_empty() {
+        Some("must not be empty".to_string())
+    } else if value.len() > MAX_IDEMPOTENCY_KEY_LENGTH {
+        Some(format!("must be at most {MAX_IDEMPOTENCY_KEY_LENGTH} characters, got {}", value.len()))
+    } else if !value.iter().all(u8::is_ascii_graphic) {
+        Some("must only contain visible ASCII characters".to_string())
+    } else {
+        None
+    };
+
+    match message {
+        Some(message) => Err(AppError::Validation(vec![FieldError::new(header_name, message)])),
+        None => Ok(String::from_utf8_lossy(value).into_owned()),
+    }
+}
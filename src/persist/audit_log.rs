@@ -0,0 +1,194 @@
+use actix_request_identifier::RequestId;
+use chrono::{DateTime, NaiveDateTime, SecondsFormat, Utc};
+use serde_json::json;
+use tracing::{error, instrument};
+use mysql::prelude::*;
+use mysql::{Pool, Row, Value};
+
+use crate::common::actor::Actor;
+use crate::common::errors::MysqlValueError::{MissingDatetime, MissingInteger, MissingString};
+use crate::common::errors::{generate_mysql_value_error, PersistenceError};
+use crate::model::requests::AuditQuery;
+use crate::model::resources::{AuditAction, AuditEntry, TableItem};
+use crate::model::time::RestaurantTimezone;
+use crate::persist::connection::get_conn;
+
+const DEFAULT_AUDIT_LIMIT: u32 = 100;
+const MAX_AUDIT_LIMIT: u32 = 1000;
+/// Actor of the entries written by administration commands, which run outside of any request.
+const CLI_ACTOR: &str = "cli";
+
+pub struct AuditRecord<'a> {
+    pub action: AuditAction,
+    pub table_number: u32,
+    pub item_id: u32,
+    pub before: Option<&'a TableItem>,
+    pub after: Option<&'a TableItem>,
+}
+
+/// Appends audit entries using the caller's connection, so they commit or roll back
+/// together with the mutation they describe.
+pub fn record_audit_entries<Q: Queryable>(
+    conn: &mut Q,
+    request_id: &RequestId,
+    actor: &Actor,
+    records: &[AuditRecord],
+) -> Result<(), mysql::Error> {
+    if records.is_empty() {
+        return Ok(());
+    }
+    let recorded_on = Utc::now().naive_utc();
+    let placeholders = vec!["(?, ?, ?, ?, ?, ?, ?, ?)"; records.len()].join(", ");
+    let query = format!(
+        "INSERT INTO audit_log (action, actor, request_id, table_number, item_id, \
+         before_snapshot, after_snapshot, recorded_on) VALUES {placeholders}"
+    );
+    let params = records.iter().flat_map(|record| {
+        vec![
+            record.action.as_str().into(),
+            actor.as_str().into(),
+            request_id.as_str().into(),
+            record.table_number.into(),
+            record.item_id.into(),
+            snapshot_value(record.before),
+            snapshot_value(record.after),
+            recorded_on.into(),
+        ]
+    }).collect::<Vec<Value>>();
+
+    conn.exec_drop(query, params)
+}
+
+/// Records an `items purge` run. It names no item: `table_number` and `item_id` are 0 and
+/// `after_snapshot` holds the cutoff and how many items were deleted. Uses the caller's
+/// connection like `record_audit_entries`.
+pub fn record_purge_entry<Q: Queryable>(
+    conn: &mut Q,
+    ordered_before: DateTime<Utc>,
+    purged_items: u64,
+) -> Result<(), mysql::Error> {
+    let recorded_on = Utc::now();
+    let summary = json!({
+        "ordered_before": ordered_before.to_rfc3339_opts(SecondsFormat::Micros, true),
+        "purged_items": purged_items,
+    });
+    conn.exec_drop(
+        "INSERT INTO audit_log (action, actor, request_id, table_number, item_id, \
+         before_snapshot, after_snapshot, recorded_on) VALUES (?, ?, ?, 0, 0, NULL, ?, ?)",
+        (
+            AuditAction::Purge.as_str(),
+            CLI_ACTOR,
+            format!("purge-{}", recorded_on.timestamp_micros()),
+            summary.to_string(),
+            recorded_on.naive_utc(),
+        ),
+    )
+}
+
+#[instrument(skip_all, err, fields(request_id = %request_id))]
+pub fn get_audit_entries(
+    pool: &Pool,
+    request_id: RequestId,
+    timezone: RestaurantTimezone,
+    audit_query: AuditQuery,
+) -> Result<Vec<AuditEntry>, PersistenceError> {
+    let (query, params) = generate_query_and_params(&timezone, audit_query);
+
+    let mut conn = get_conn(pool, "get_audit_entries")?;
+    let result = match conn.exec_iter(query, params) {
+        Ok(result) => Ok(result
+            .map(convert_row_to_audit_entry)
+            .filter_map(Result::ok)
+            .collect()),
+        Err(e) => {
+            error!(error = ?e, "Audit log retrieving failed");
+            Err(PersistenceError::DBOpError)
+        }
+    };
+    result
+}
+
+fn snapshot_value(item: Option<&TableItem>) -> Value {
+    item.and_then(|item| serde_json::to_string(item).ok())
+        .map(Value::from)
+        .unwrap_or(Value::NULL)
+}
+
+fn generate_query_and_params(timezone: &RestaurantTimezone, audit_query: AuditQuery) -> (String, Vec<Value>) {
+    let mut query = String::from(
+        "SELECT audit_id, action, actor, request_id, table_number, item_id, \
+         before_snapshot, after_snapshot, recorded_on FROM audit_log"
+    );
+    let mut params: Vec<Value> = vec![];
+    let mut conditions = Vec::new();
+
+    if let Some(table_number) = audit_query.table_number {
+        conditions.push("table_number = ?");
+        params.push(table_number.into());
+    }
+    if let Some(item_id) = audit_query.item_id {
+        conditions.push("item_id = ?");
+        params.push(item_id.into());
+    }
+    if let Some(action) = audit_query.action {
+        conditions.push("action = ?");
+        params.push(action.as_str().into());
+    }
+    if let Some(actor) = audit_query.actor {
+        conditions.push("actor = ?");
+        params.push(actor.into());
+    }
+    if let Some(request_id) = audit_query.request_id {
+        conditions.push("request_id = ?");
+        params.push(request_id.into());
+    }
+    if let Some(from) = audit_query.from {
+        conditions.push("recorded_on >= ?");
+        params.push(timezone.resolve(&from).naive_utc().into());
+    }
+    if let Some(to) = audit_query.to {
+        conditions.push("recorded_on < ?");
+        params.push(timezone.resolve(&to).naive_utc().into());
+    }
+
+    if !conditions.is_empty() {
+        query.push_str(" WHERE ");
+        query.push_str(&conditions.join(" AND "));
+    }
+    let limit = audit_query.limit.unwrap_or(DEFAULT_AUDIT_LIMIT).clamp(1, MAX_AUDIT_LIMIT);
+    query.push_str(&format!(" ORDER BY audit_id DESC LIMIT {limit}"));
+    (query, params)
+}
+
+fn convert_row_to_audit_entry(row: Result<Row, mysql::Error>) -> Result<AuditEntry, mysql::Error> {
+    let row = row?;
+    let audit_id: u64 = row.get(0)
+        .ok_or_else(|| generate_mysql_value_error(MissingInteger, "audit_id".into()))?;
+    let action: String = row.get(1)
+        .ok_or_else(|| generate_mysql_value_error(MissingString, "action".into()))?;
+    let actor: String = row.get(2)
+        .ok_or_else(|| generate_mysql_value_error(MissingString, "actor".into()))?;
+    let request_id: String = row.get(3)
+        .ok_or_else(|| generate_mysql_value_error(MissingString, "request_id".into()))?;
+    let table_number: u32 = row.get(4)
+        .ok_or_else(|| generate_mysql_value_error(MissingInteger, "table_number".into()))?;
+    let item_id: u32 = row.get(5)
+        .ok_or_else(|| generate_mysql_value_error(MissingInteger, "item_id".into()))?;
+    let before: Option<String> = row.get(6).unwrap_or(None);
+    let after: Option<String> = row.get(7).unwrap_or(None);
+    let recorded_on = row.get::<NaiveDateTime, _>(8)
+        .map(|recorded_on| recorded_on.and_utc())
+        .ok_or_else(|| generate_mysql_value_error(MissingDatetime, "recorded_on".into()))?;
+
+    Ok(AuditEntry {
+        audit_id,
+        action,
+        actor,
+        request_id,
+        table_number,
+        item_id,
+        before: before.and_then(|snapshot| serde_json::from_str(&snapshot).ok()),
+        after: after.and_then(|snapshot| serde_json::from_str(&snapshot).ok()),
+        recorded_on,
+    })
+}
@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+
+use actix_request_identifier::RequestId;
+use tracing::{error, instrument};
+use mysql::prelude::*;
+use mysql::Pool;
+use crate::common::actor::Actor;
+use crate::common::etag::Precondition;
+use crate::common::errors::PersistenceError;
+use crate::common::metrics::METRICS;
+use crate::model::requests::{BulkItemRef, BulkMode};
+use crate::model::resources::{AuditAction, ItemStatus, TableItem, VoidReason};
+use crate::model::responses::{BulkItemOutcome, BulkItemResult, BulkOperationResponse};
+use crate::persist::connection::get_conn;
+use crate::persist::audit_log::{record_audit_entries, AuditRecord};
+use crate::persist::fetch_table_items::select_item_for_update;
+use crate::persist::retry::{op_error, retry_transient};
+use crate::persist::transaction::Transaction;
+use crate::persist::void_table_items::void_item;
+
+#[instrument(skip_all, err, fields(request_id = %request_id, table_number = table_number, item_ids = ?items.iter().map(|item| item.item_id).collect::<Vec<_>>()))]
+pub fn void_table_items(
+    pool: &Pool,
+    request_id: RequestId,
+    actor: Actor,
+    table_number: u32,
+    items: Vec<BulkItemRef>,
+    reason: VoidReason,
+    mode: BulkMode,
+) -> Result<BulkOperationResponse, PersistenceError> {
+    // By item id: a retried batch voids the same items again.
+    let mut voided_names = HashMap::new();
+    let response = run_bulk(pool, "void_table_items", mode, &items, |conn, item_id, precondition| {
+        void_item(conn, &request_id, &actor, table_number, item_id, reason, &precondition)
+            .inspect(|voided| { voided_names.insert(item_id, voided.item_name.clone()); })
+    })?;
+    if response.committed {
+        response.results.iter()
+            .filter(|result| result.outcome == BulkItemOutcome::Applied)
+            .filter_map(|result| voided_names.get(&result.item_id))
+            .for_each(|item_name| METRICS.item_removed(item_name, reason));
+    }
+    Ok(response)
+}
+
+#[instrument(skip_all, err, fields(request_id = %request_id, table_number = table_number, item_ids = ?items.iter().map(|item| item.item_id).collect::<Vec<_>>()))]
+pub fn update_table_items_status(
+    pool: &Pool,
+    request_id: RequestId,
+    actor: Actor,
+    table_number: u32,
+    items: Vec<BulkItemRef>,
+    status: ItemStatus,
+    mode: BulkMode,
+) -> Result<BulkOperationResponse, PersistenceError> {
+    run_bulk(pool, "update_table_items_status", mode, &items, |conn, item_id, precondition| {
+        update_item_status(conn, &request_id, &actor, table_number, item_id, status, precondition)
+    })
+}
+
+/// Applies `apply` to every item inside one transaction and records what happened to each.
+/// Item level failures never stop the batch, so the response always covers every item;
+/// in all-or-nothing mode a single failure rolls the whole batch back. Storage errors abort,
+/// deadlocks and lock wait timeouts after the batch was retried.
+/// `function` is the public operation, labelling its connection, transaction and retry metrics.
+fn run_bulk<F>(
+    pool: &Pool,
+    function: &'static str,
+    mode: BulkMode,
+    items: &[BulkItemRef],
+    mut apply: F,
+) -> Result<BulkOperationResponse, PersistenceError>
+where
+    F: FnMut(&mut Transaction, u32, Precondition) -> Result<TableItem, PersistenceError>,
+{
+    let mut conn = get_conn(pool, function)?;
+    retry_transient(function, || {
+        let mut tx = Transaction::begin(&mut conn)?;
+
+        let mut results = Vec::with_capacity(items.len());
+        for item in items {
+            let precondition = item.version
+                .map(|version| Precondition::Versions(vec![version]))
+                .unwrap_or(Precondition::Any);
+            let (outcome, version) = match apply(&mut tx, item.item_id, precondition) {
+                Ok(applied) => (BulkItemOutcome::Applied, Some(applied.version)),
+                Err(PersistenceError::ResourceNotFound) => (BulkItemOutcome::NotFound, None),
+                Err(PersistenceError::ResourceConflict) => (BulkItemOutcome::Conflict, None),
+                Err(PersistenceError::VersionMismatch) => (BulkItemOutcome::VersionMismatch, None),
+                Err(e) => {
+                    tx.rollback()?;
+                    return Err(e);
+                }
+            };
+            results.push(BulkItemResult { item_id: item.item_id, outcome, version });
+        }
+
+        let failed = results.iter().any(|result| result.outcome != BulkItemOutcome::Applied);
+        let committed = !(failed && mode == BulkMode::AllOrNothing);
+        if committed {
+            tx.commit()?;
+        } else {
+            error!("Bulk operation rolled back");
+            tx.rollback()?;
+            // Nothing was written, so no item carries a new version.
+            for result in results.iter_mut() {
+                result.version = None;
+            }
+        }
+
+        Ok(BulkOperationResponse { mode, committed, results })
+    })
+}
+
+/// Moves a live item to another kitchen status. Voided items are gone from the table,
+/// so they are reported as not found, like in the listings.
+fn update_item_status(
+    conn: &mut Transaction,
+    request_id: &RequestId,
+    actor: &Actor,
+    table_number: u32,
+    item_id: u32,
+    status: ItemStatus,
+    precondition: Precondition,
+) -> Result<TableItem, PersistenceError> {
+    let before = select_item_for_update(conn, table_number, item_id, &precondition)?;
+    if before.voided_on.is_some() {
+        error!(item_id, "Item is voided");
+        return Err(PersistenceError::ResourceNotFound);
+    }
+    if before.status == status {
+        error!(item_id, status = status.as_str(), "Item already has the status");
+        return Err(PersistenceError::ResourceConflict);
+    }
+
+    let after = TableItem {
+        status,
+        version: before.version + 1,
+        ..before.clone()
+    };
+    let audit_record = AuditRecord {
+        action: AuditAction::Update,
+        table_number,
+        item_id,
+        before: Some(&before),
+        after: Some(&after),
+    };
+    conn.exec_drop(generate_status_query(), (status.as_str(), table_number, item_id,))
+        .and_then(|_| record_audit_entries(conn, request_id, actor, &[audit_record]))
+        .map_err(|e| {
+            error!(error = ?e, "Updating item status failed");
+            op_error(&e)
+        })?;
+    Ok(after)
+}
+
+fn generate_status_query() -> String {
+    "UPDATE table_items SET status = ?, version = version + 1 WHERE table_number = ? and item_id = ?".to_string()
+}
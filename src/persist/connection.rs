@@ -0,0 +1,122 @@
+use std::ops::{Deref, DerefMut};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use mysql::{DriverError, Error, Opts, OptsBuilder, Pool, PoolConstraints, PoolOpts, PooledConn, SslOpts};
+use tracing::{error, info, warn};
+
+use crate::common::errors::PersistenceError;
+use crate::common::metrics::METRICS;
+use crate::config::{DatabaseConfig, TlsMode};
+
+const FIRST_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+const DEFAULT_POOL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Connections checked out, i.e. database work in progress; shutdown waits for it to drop to 0.
+static IN_USE: AtomicUsize = AtomicUsize::new(0);
+/// How long `get_conn` waits for a connection when the pool is exhausted, set by `connect`.
+static POOL_TIMEOUT: OnceLock<Duration> = OnceLock::new();
+
+/// Creates the pool, retrying with exponential backoff while MySQL can not be reached, e.g.
+/// when the service starts before the database. Errors MySQL itself answers are not retried.
+pub fn connect(database: &DatabaseConfig) -> Result<Pool, String> {
+    let opts = opts(database)?;
+    let _ = POOL_TIMEOUT.set(Duration::from_secs(database.pool_timeout_seconds));
+    let mut backoff = FIRST_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        // A pool with no minimum size does not connect until asked to.
+        match Pool::new(opts.clone()).and_then(|pool| pool.get_conn().map(|_| pool)) {
+            Ok(pool) => {
+                info!(attempt, "Connected to MySQL");
+                return Ok(pool);
+            }
+            Err(e @ Error::MySqlError(_)) => return Err(format!("MySQL refused the connection: {e}")),
+            Err(e) if attempt < database.connect_attempts => {
+                warn!(error = %e, attempt, backoff_ms = backoff.as_millis() as u64, "Can NOT connect to MySQL, retrying");
+                thread::sleep(backoff);
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+                attempt += 1;
+            }
+            Err(e) => return Err(format!("Can NOT connect to MySQL after {attempt} attempts: {e}")),
+        }
+    }
+}
+
+fn opts(database: &DatabaseConfig) -> Result<Opts, String> {
+    let constraints = PoolConstraints::new(database.pool_min, database.pool_max)
+        .ok_or_else(|| format!("Invalid pool size {}..{}", database.pool_min, database.pool_max))?;
+    let ssl_opts = match database.tls_mode {
+        TlsMode::Disabled => None,
+        mode => Some(SslOpts::default()
+            .with_root_cert_path(database.tls_ca.as_ref().map(PathBuf::from))
+            .with_danger_accept_invalid_certs(mode == TlsMode::Required)
+            .with_danger_skip_domain_validation(mode != TlsMode::VerifyIdentity)),
+    };
+    Ok(OptsBuilder::new()
+        .ip_or_hostname(Some(&database.host))
+        .tcp_port(database.port)
+        .db_name(Some(&database.name))
+        .user(Some(&database.user))
+        .pass(Some(&database.password))
+        .pool_opts(PoolOpts::default().with_constraints(constraints))
+        .tcp_connect_timeout(Some(Duration::from_secs(database.connect_timeout_seconds)))
+        .read_timeout(Some(Duration::from_secs(database.read_timeout_seconds)))
+        .ssl_opts(ssl_opts)
+        .into())
+}
+
+/// Pool connection checked out by a persistence function. Records the wait for it and,
+/// once dropped, how long the function held it.
+pub(crate) struct Connection {
+    conn: PooledConn,
+    pub(super) function: &'static str,
+    checked_out: Instant,
+}
+
+pub(crate) fn get_conn(pool: &Pool, function: &'static str) -> Result<Connection, PersistenceError> {
+    let started = Instant::now();
+    let timeout = *POOL_TIMEOUT.get().unwrap_or(&DEFAULT_POOL_TIMEOUT);
+    let conn = pool.try_get_conn(timeout).map_err(|e| {
+        match e {
+            Error::DriverError(DriverError::Timeout) => warn!(function, "Pool exhausted, no connection available"),
+            e => error!(error = ?e, function, "Can NOT connect to MySQL"),
+        }
+        PersistenceError::DBConnError
+    })?;
+    METRICS.db_pool_wait.observe(started.elapsed().as_secs_f64());
+    METRICS.db_connections_in_use.inc();
+    IN_USE.fetch_add(1, Ordering::SeqCst);
+    Ok(Connection { conn, function, checked_out: Instant::now() })
+}
+
+impl Deref for Connection {
+    type Target = PooledConn;
+
+    fn deref(&self) -> &PooledConn {
+        &self.conn
+    }
+}
+
+impl DerefMut for Connection {
+    fn deref_mut(&mut self) -> &mut PooledConn {
+        &mut self.conn
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        METRICS.db_connections_in_use.dec();
+        IN_USE.fetch_sub(1, Ordering::SeqCst);
+        METRICS.db_function_duration.with_label_values(&[self.function])
+            .observe(self.checked_out.elapsed().as_secs_f64());
+    }
+}
+
+pub fn connections_in_use() -> usize {
+    IN_USE.load(Ordering::SeqCst)
+}
@@ -1,13 +1,38 @@
 use actix_request_identifier::RequestId;
-use log::error;
+use tracing::{error, instrument};
 use mysql::prelude::*;
 use mysql::{from_value, Pool, Value, Row};
-use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use chrono::{DateTime, NaiveDateTime, Utc};
 
+use crate::common::etag::Precondition;
 use crate::common::errors::MysqlValueError::{MissingDatetime, MissingInteger, MissingString};
 use crate::common::errors::{generate_mysql_value_error, PersistenceError};
-use crate::model::resources::TableItem;
+use crate::model::pagination::{PageRequest, SortField};
+use crate::model::requests::VoidReportQuery;
+use crate::model::resources::{ItemStatus, TableItem, VoidReason};
+use crate::model::time::RestaurantTimezone;
+use crate::persist::connection::get_conn;
+use crate::persist::retry::op_error;
+use crate::persist::transaction::Transaction;
 
+#[derive(Debug, Default)]
+pub struct ItemsFilter {
+    pub items_ids: Option<Vec<u32>>,
+    pub items_names: Option<Vec<String>>,
+    pub name_contains: Option<String>,
+    pub status: Option<ItemStatus>,
+    pub ordered_from: Option<DateTime<Utc>>,
+    pub ordered_to: Option<DateTime<Utc>>,
+    pub overdue: Option<bool>,
+}
+
+pub struct ItemsPage {
+    pub items: Vec<TableItem>,
+    /// More rows exist past this page in the direction it was fetched.
+    pub has_more: bool,
+}
+
+#[instrument(skip_all, err, fields(request_id = %request_id, table_number = table_number, item_ids = ?items_ids))]
 pub fn get_table_items(
     pool: &Pool,
     request_id: RequestId,
@@ -15,60 +40,272 @@ pub fn get_table_items(
     items_ids: Option<Vec<u32>>,
     items_names: Option<Vec<String>>,
 ) -> Result<Vec<TableItem>, PersistenceError> {
-    let (query, params) = generate_query_and_params(table_number, items_ids, items_names);
+    let filter = ItemsFilter { items_ids, items_names, ..Default::default() };
+    let (query, params) = generate_query_and_params(table_number, &filter, None);
 
-    let mut conn = pool.get_conn().map_err(|_| PersistenceError::DBConnError)?;
+    let mut conn = get_conn(pool, "get_table_items")?;
     let result = match conn.exec_iter(query, params) {
         Ok(result) => {
             let table_items: Vec<TableItem> = result
-                .map(|row| convert_row_to_table_item(row))
+                .map(convert_row_to_table_item)
                 .filter_map(Result::ok)
                 .collect();
 
             if table_items.is_empty() {
-                error!("Items NOT found, request ID: {request_id}");
+                error!("Items NOT found");
                 Err(PersistenceError::ResourceNotFound)
             } else {
                 Ok(table_items)
             }
         }
         Err(e) => {
-            error!("DB retriving failed for {request_id}: {:?}", e);
+            error!(error = ?e, "DB retriving failed");
+            Err(PersistenceError::DBOpError)
+        }
+    };
+    result
+}
+
+/// Live items of the table. A table whose items were all voided is still a table, served empty
+/// so clients polling it with `If-None-Match` see the change; one that never had items is not found.
+#[instrument(skip_all, err, fields(request_id = %request_id, table_number = table_number))]
+pub fn get_table_contents(
+    pool: &Pool,
+    request_id: RequestId,
+    table_number: u32,
+) -> Result<Vec<TableItem>, PersistenceError> {
+    let (query, params) = generate_query_and_params(table_number, &ItemsFilter::default(), None);
+
+    let mut conn = get_conn(pool, "get_table_contents")?;
+    let table_items: Vec<TableItem> = conn.exec_iter(query, params)
+        .map(|result| result.map(convert_row_to_table_item).filter_map(Result::ok).collect())
+        .map_err(|e| {
+            error!(error = ?e, "DB retriving failed");
+            PersistenceError::DBOpError
+        })?;
+    if !table_items.is_empty() {
+        return Ok(table_items);
+    }
+
+    let has_voided_items = conn.exec_first::<bool, _, _>(generate_table_exists_query(), (table_number,))
+        .map_err(|e| {
+            error!(error = ?e, "DB retriving failed");
+            PersistenceError::DBOpError
+        })?
+        .unwrap_or(false);
+    if has_voided_items {
+        Ok(table_items)
+    } else {
+        error!("Table NOT found");
+        Err(PersistenceError::ResourceNotFound)
+    }
+}
+
+#[instrument(skip_all, err, fields(request_id = %request_id, table_number = table_number, item_ids = ?filter.items_ids))]
+pub fn get_table_items_page(
+    pool: &Pool,
+    request_id: RequestId,
+    table_number: u32,
+    filter: ItemsFilter,
+    page: PageRequest,
+) -> Result<ItemsPage, PersistenceError> {
+    let (query, params) = generate_query_and_params(table_number, &filter, Some(&page));
+
+    let mut conn = get_conn(pool, "get_table_items_page")?;
+    let result = match conn.exec_iter(query, params) {
+        Ok(result) => {
+            let mut items: Vec<TableItem> = result
+                .map(convert_row_to_table_item)
+                .filter_map(Result::ok)
+                .collect();
+
+            let has_more = items.len() > page.limit as usize;
+            items.truncate(page.limit as usize);
+            if page.is_backward() {
+                items.reverse();
+            }
+            Ok(ItemsPage { items, has_more })
+        }
+        Err(e) => {
+            error!(error = ?e, "DB retriving page failed");
+            Err(PersistenceError::DBOpError)
+        }
+    };
+    result
+}
+
+#[instrument(skip_all, err, fields(request_id = %request_id))]
+pub fn get_voided_items(
+    pool: &Pool,
+    request_id: RequestId,
+    timezone: RestaurantTimezone,
+    report_query: VoidReportQuery,
+) -> Result<Vec<TableItem>, PersistenceError> {
+    let (query, params) = generate_voided_query_and_params(&timezone, report_query);
+
+    let mut conn = get_conn(pool, "get_voided_items")?;
+    let result = match conn.exec_iter(query, params) {
+        Ok(result) => Ok(result
+            .map(convert_row_to_table_item)
+            .filter_map(Result::ok)
+            .collect()),
+        Err(e) => {
+            error!(error = ?e, "DB retriving voided items failed");
             Err(PersistenceError::DBOpError)
         }
     };
     result
 }
 
-fn generate_query_and_params(table_number: u32, items_ids: Option<Vec<u32>>,
-                             items_names: Option<Vec<String>>,
+/// Locks the item row for the rest of the transaction and checks the client's `If-Match` version.
+pub(crate) fn select_item_for_update(
+    conn: &mut Transaction,
+    table_number: u32,
+    item_id: u32,
+    precondition: &Precondition,
+) -> Result<TableItem, PersistenceError> {
+    let item = conn.exec_first::<Row, _, _>(generate_select_for_update_query(), (table_number, item_id,))
+        .and_then(|row| row.map(|row| convert_row_to_table_item(Ok(row))).transpose())
+        .map_err(|e| {
+            error!(error = ?e, "DB retriving failed");
+            op_error(&e)
+        })?;
+    let item = item.ok_or_else(|| {
+        error!("Item NOT found");
+        PersistenceError::ResourceNotFound
+    })?;
+    if !precondition.matches(item.version) {
+        error!(item_id, version = item.version, "Item version does NOT match If-Match");
+        return Err(PersistenceError::VersionMismatch);
+    }
+    Ok(item)
+}
+
+fn generate_table_exists_query() -> String {
+    "SELECT EXISTS(SELECT 1 FROM table_items WHERE table_number = ?)".to_string()
+}
+
+fn generate_select_for_update_query() -> String {
+    "SELECT * FROM table_items WHERE table_number = ? and item_id = ? FOR UPDATE".to_string()
+}
+
+fn generate_voided_query_and_params(timezone: &RestaurantTimezone, report_query: VoidReportQuery) -> (String, Vec<Value>) {
+    let mut query = String::from("SELECT * FROM table_items WHERE voided_on IS NOT NULL");
+    let mut params: Vec<Value> = vec![];
+
+    if let Some(table_number) = report_query.table_number {
+        query.push_str(" AND table_number = ?");
+        params.push(table_number.into());
+    }
+    if let Some(reason) = report_query.reason {
+        query.push_str(" AND void_reason = ?");
+        params.push(reason.as_str().into());
+    }
+    if let Some(from) = report_query.from {
+        query.push_str(" AND voided_on >= ?");
+        params.push(timezone.resolve(&from).naive_utc().into());
+    }
+    if let Some(to) = report_query.to {
+        query.push_str(" AND voided_on < ?");
+        params.push(timezone.resolve(&to).naive_utc().into());
+    }
+    query.push_str(" ORDER BY voided_on DESC");
+    (query, params)
+}
+
+fn generate_query_and_params(table_number: u32, filter: &ItemsFilter, page: Option<&PageRequest>,
 ) -> (String, Vec<Value>) {
-    let mut query = String::from("SELECT * FROM table_items WHERE table_number = ?");
+    let mut query = String::from("SELECT * FROM table_items WHERE table_number = ? AND voided_on IS NULL");
     let mut params: Vec<Value> = vec![Value::from(table_number)];
     let mut conditions = Vec::new();
 
-    if let Some(ref items_ids) = items_ids {
+    if let Some(ref items_ids) = filter.items_ids {
         if !items_ids.is_empty() {
             conditions.push(format!("item_id IN ({})", vec!["?"; items_ids.len()].join(",")));
             params.extend(items_ids.iter().map(|&id| Value::from(id)));
         }
     }
 
-    if let Some(ref items_names) = items_names {
+    if let Some(ref items_names) = filter.items_names {
         if !items_names.is_empty() {
             conditions.push(format!("item_name IN ({})", vec!["?"; items_names.len()].join(",")));
-            params.extend(items_names.iter().map(|name| Value::from(name)));
+            params.extend(items_names.iter().map(Value::from));
         }
     }
 
-    if !conditions.is_empty() {
+    // Values of one filter are alternatives (IN), distinct filters all have to match.
+    for condition in conditions {
         query.push_str(" AND ");
-        query.push_str(&format!("( {} )", conditions.join(" OR ")));
+        query.push_str(&condition);
+    }
+
+    if let Some(ref name_contains) = filter.name_contains {
+        query.push_str(" AND item_name LIKE ?");
+        params.push(format!("%{}%", escape_like(name_contains)).into());
+    }
+    if let Some(status) = filter.status {
+        query.push_str(" AND status = ?");
+        params.push(status.as_str().into());
+    }
+    if let Some(ordered_from) = filter.ordered_from {
+        query.push_str(" AND ordered_on >= ?");
+        params.push(ordered_from.naive_utc().into());
+    }
+    if let Some(ordered_to) = filter.ordered_to {
+        query.push_str(" AND ordered_on < ?");
+        params.push(ordered_to.naive_utc().into());
+    }
+    if let Some(overdue) = filter.overdue {
+        // Mirrors `TableItem::is_overdue`; voided items are already excluded.
+        if overdue {
+            query.push_str(" AND status <> 'served' AND DATE_ADD(ordered_on, INTERVAL prepare_minutes MINUTE) < ?");
+        } else {
+            query.push_str(" AND (status = 'served' OR DATE_ADD(ordered_on, INTERVAL prepare_minutes MINUTE) >= ?)");
+        }
+        params.push(Utc::now().naive_utc().into());
+    }
+
+    if let Some(page) = page {
+        let column = page.sort.field.column();
+        // Walking backwards flips both the comparison and the order; the page is reversed after fetching.
+        let ascending = page.sort.descending == page.is_backward();
+        let comparison = if ascending { ">" } else { "<" };
+        if let Some(cursor) = page.after.as_ref().or(page.before.as_ref()) {
+            let cursor_value = match &cursor.value {
+                serde_json::Value::Number(number) => Value::from(number.as_u64().unwrap_or_default()),
+                // Timestamps travel as RFC 3339 in the cursor but are compared as stored, in naive UTC.
+                serde_json::Value::String(string) if page.sort.field == SortField::OrderedOn =>
+                    DateTime::parse_from_rfc3339(string)
+                        .map(|at| Value::from(at.naive_utc()))
+                        .unwrap_or(Value::NULL),
+                serde_json::Value::String(string) => Value::from(string),
+                _ => Value::NULL,
+            };
+            if page.sort.field == SortField::ItemId {
+                query.push_str(&format!(" AND item_id {comparison} ?"));
+                params.push(cursor.item_id.into());
+            } else {
+                query.push_str(&format!(" AND ({column} {comparison} ? OR ({column} = ? AND item_id {comparison} ?))"));
+                params.extend([cursor_value.clone(), cursor_value, cursor.item_id.into()]);
+            }
+        }
+        let direction = if ascending { "ASC" } else { "DESC" };
+        if page.sort.field == SortField::ItemId {
+            query.push_str(&format!(" ORDER BY item_id {direction}"));
+        } else {
+            query.push_str(&format!(" ORDER BY {column} {direction}, item_id {direction}"));
+        }
+        // One extra row tells whether another page follows.
+        query.push_str(&format!(" LIMIT {}", page.limit + 1));
     }
     (query, params)
 }
 
-fn convert_row_to_table_item(row: Result<Row, mysql::Error>) -> Result<TableItem, mysql::Error> {
+fn escape_like(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+pub(crate) fn convert_row_to_table_item(row: Result<Row, mysql::Error>) -> Result<TableItem, mysql::Error> {
     let row = row?;
     let item_id: u32 = row.get(0)
         .ok_or_else(|| generate_mysql_value_error(MissingInteger, "item_id".into()))?;
@@ -77,7 +314,7 @@ fn convert_row_to_table_item(row: Result<Row, mysql::Error>) -> Result<TableItem
         .ok_or_else(|| generate_mysql_value_error(MissingInteger, "table_number".into()))?;
 
     let item_name: String = match row.get(2) {
-        Some(Value::Bytes(bytes)) => String::from_utf8_lossy(&*bytes).into_owned(),
+        Some(Value::Bytes(bytes)) => String::from_utf8_lossy(&bytes).into_owned(),
         Some(other) => from_value(other),
         None => return Err(generate_mysql_value_error(MissingString, "item_name".into())),
     };
@@ -85,16 +322,25 @@ fn convert_row_to_table_item(row: Result<Row, mysql::Error>) -> Result<TableItem
     let prepare_minutes: u32 = row.get(3)
         .ok_or_else(|| generate_mysql_value_error(MissingInteger, "prepare_minutes".into()))?;
 
-    let ordered_on: String = match row.get(4) {
-        Some(Value::Date(year, month, day, hour, minute, second, _micro_second)) => {
-            NaiveDateTime::new(
-                NaiveDate::from_ymd_opt(year as i32, month as u32, day as u32).unwrap(),
-                NaiveTime::from_hms_opt(hour as u32, minute as u32, second as u32).unwrap(),
-            ).format("%Y-%m-%d %H:%M:%S").to_string()
-        }
-        Some(other) => from_value(other),
-        None => return Err(generate_mysql_value_error(MissingDatetime, "ordered_on".into())),
-    };
+    // Stored as naive UTC.
+    let ordered_on: DateTime<Utc> = row.get::<NaiveDateTime, _>(4)
+        .map(|ordered_on| ordered_on.and_utc())
+        .ok_or_else(|| generate_mysql_value_error(MissingDatetime, "ordered_on".into()))?;
+
+    let voided_on: Option<DateTime<Utc>> = row.get::<Option<NaiveDateTime>, _>(5)
+        .flatten()
+        .map(|voided_on| voided_on.and_utc());
+
+    let void_reason: Option<VoidReason> = row.get::<Option<String>, _>(6)
+        .flatten()
+        .and_then(|void_reason| VoidReason::parse(&void_reason));
+
+    let version: u32 = row.get(7)
+        .ok_or_else(|| generate_mysql_value_error(MissingInteger, "version".into()))?;
+
+    let status: ItemStatus = row.get::<String, _>(8)
+        .and_then(|status| ItemStatus::parse(&status))
+        .ok_or_else(|| generate_mysql_value_error(MissingString, "status".into()))?;
 
     Ok(TableItem {
         item_id,
@@ -102,5 +348,9 @@ fn convert_row_to_table_item(row: Result<Row, mysql::Error>) -> Result<TableItem
         item_name,
         prepare_minutes,
         ordered_on,
+        voided_on,
+        void_reason,
+        version,
+        status,
     })
 }
\ No newline at end of file
@@ -0,0 +1,21 @@
+use std::time::Duration;
+
+use tracing::error;
+use mysql::prelude::*;
+use mysql::{Pool, PooledConn};
+
+use crate::common::errors::PersistenceError;
+
+/// Borrows a connection within `timeout` and round-trips a query on it, so a connection the
+/// server already dropped is not reported as healthy.
+pub fn ping(pool: &Pool, timeout: Duration) -> Result<PooledConn, PersistenceError> {
+    let mut conn = pool.try_get_conn(timeout).map_err(|e| {
+        error!(error = ?e, "No connection available for the health check");
+        PersistenceError::DBConnError
+    })?;
+    conn.query_drop("SELECT 1").map_err(|e| {
+        error!(error = ?e, "Health check query failed");
+        PersistenceError::DBOpError
+    })?;
+    Ok(conn)
+}
@@ -0,0 +1,136 @@
+use chrono::{Duration, Utc};
+use mysql::prelude::*;
+use mysql::Row;
+use serde::Serialize;
+
+const DUPLICATE_ENTRY_ERROR_CODE: u16 = 1062;
+/// Expired keys deleted per sweep; small enough to never hold many row locks at once.
+const SWEEP_BATCH_SIZE: u32 = 100;
+
+#[derive(Debug, Serialize)]
+pub struct IdempotencyConfig {
+    pub ttl_seconds: u64,
+}
+
+/// Client supplied `Idempotency-Key` together with how long its response is replayed.
+pub struct IdempotencyKey {
+    pub key: String,
+    pub ttl_seconds: u64,
+}
+
+pub enum IdempotencyClaim {
+    /// First use of the key: the caller performs the request and stores its response.
+    Claimed,
+    /// The key was already used for the same request; the stored response is replayed.
+    Replay(String),
+    /// The key was already used for a different request.
+    Mismatch,
+}
+
+/// Claims the key inside the caller's transaction. A concurrent request holding the same
+/// key blocks on the primary key until it commits, so retries never run the request twice.
+pub fn claim_idempotency_key<Q: Queryable>(
+    conn: &mut Q,
+    idempotency_key: &IdempotencyKey,
+    request_fingerprint: &str,
+) -> Result<IdempotencyClaim, mysql::Error> {
+    let now = Utc::now().naive_utc();
+    let expired_before = now - Duration::seconds(idempotency_key.ttl_seconds as i64);
+    conn.exec_drop(
+        "DELETE FROM idempotency_keys WHERE idempotency_key = ? AND created_on < ?",
+        (&idempotency_key.key, expired_before,),
+    )?;
+
+    let inserted = conn.exec_drop(
+        "INSERT INTO idempotency_keys (idempotency_key, request_fingerprint, created_on) VALUES (?, ?, ?)",
+        (&idempotency_key.key, request_fingerprint, now,),
+    );
+    match inserted {
+        Ok(_) => Ok(IdempotencyClaim::Claimed),
+        Err(mysql::Error::MySqlError(e)) if e.code == DUPLICATE_ENTRY_ERROR_CODE => {
+            let stored = conn.exec_first::<Row, _, _>(
+                "SELECT request_fingerprint, response_body FROM idempotency_keys WHERE idempotency_key = ? FOR UPDATE",
+                (&idempotency_key.key,),
+            )?;
+            let (stored_fingerprint, response_body): (Option<String>, Option<String>) = stored
+                .map(|row| (row.get(0), row.get::<Option<String>, _>(1).flatten()))
+                .unwrap_or((None, None));
+            match (stored_fingerprint, response_body) {
+                (Some(stored_fingerprint), Some(response_body)) if stored_fingerprint == request_fingerprint =>
+                    Ok(IdempotencyClaim::Replay(response_body)),
+                _ => Ok(IdempotencyClaim::Mismatch),
+            }
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Deletes up to `SWEEP_BATCH_SIZE` keys older than the TTL, oldest first. Run on every claim outside
+/// of its transaction, it keeps the table bounded by the keys claimed within one TTL.
+pub fn sweep_expired_idempotency_keys<Q: Queryable>(conn: &mut Q, ttl_seconds: u64) -> Result<u64, mysql::Error> {
+    let expired_before = Utc::now().naive_utc() - Duration::seconds(ttl_seconds as i64);
+    conn.exec_iter(
+        "DELETE FROM idempotency_keys WHERE created_on < ? ORDER BY created_on LIMIT ?",
+        (expired_before, SWEEP_BATCH_SIZE,),
+    ).map(|result| result.affected_rows())
+}
+
+pub fn store_idempotent_response<Q: Queryable>(
+    conn: &mut Q,
+    idempotency_key: &IdempotencyKey,
+    response_body: &str,
+) -> Result<(), mysql::Error> {
+    conn.exec_drop(
+        "UPDATE idempotency_keys SET response_body = ? WHERE idempotency_key = ?",
+        (response_body, &idempotency_key.key,),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use mysql::prelude::Queryable;
+    use mysql::{Pool, PooledConn};
+
+    use super::sweep_expired_idempotency_keys;
+    use crate::config::Config;
+    use crate::persist::connection::connect;
+
+    const TTL_SECONDS: u64 = 60;
+
+    fn conn() -> PooledConn {
+        dotenvy::dotenv().ok();
+        let config = Config::load(None).expect("invalid configuration");
+        let pool: Pool = connect(&config.database).expect("MySQL is not reachable");
+        pool.get_conn().unwrap()
+    }
+
+    fn insert_key(conn: &mut PooledConn, key: &str, age_seconds: i64) {
+        conn.exec_drop(
+            "INSERT INTO idempotency_keys (idempotency_key, request_fingerprint, created_on) VALUES (?, 'test', ?)",
+            (key, Utc::now().naive_utc() - Duration::seconds(age_seconds),),
+        ).unwrap();
+    }
+
+    fn key_exists(conn: &mut PooledConn, key: &str) -> bool {
+        conn.exec_first("SELECT EXISTS(SELECT 1 FROM idempotency_keys WHERE idempotency_key = ?)", (key,))
+            .unwrap()
+            .unwrap_or(false)
+    }
+
+    #[test]
+    #[ignore = "needs the MySQL configured in .env"]
+    fn sweeps_expired_keys_only() {
+        let mut conn = conn();
+        let expired = format!("sweep-expired-{}", Utc::now().timestamp_micros());
+        let fresh = format!("sweep-fresh-{}", Utc::now().timestamp_micros());
+        insert_key(&mut conn, &expired, TTL_SECONDS as i64 + 1);
+        insert_key(&mut conn, &fresh, 0);
+
+        while sweep_expired_idempotency_keys(&mut conn, TTL_SECONDS).unwrap() > 0 {}
+
+        assert!(!key_exists(&mut conn, &expired));
+        assert!(key_exists(&mut conn, &fresh));
+        conn.exec_drop("DELETE FROM idempotency_keys WHERE idempotency_key = ?", (&fresh,)).unwrap();
+    }
+}
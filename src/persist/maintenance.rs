@@ -0,0 +1,94 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use tracing::error;
+use mysql::prelude::*;
+use mysql::{Pool, Value};
+
+use crate::common::errors::PersistenceError;
+use crate::model::resources::TableItem;
+use crate::persist::audit_log::record_purge_entry;
+use crate::persist::connection::get_conn;
+use crate::persist::fetch_table_items::convert_row_to_table_item;
+use crate::persist::transaction::in_transaction;
+
+/// Tables with items that are not voided.
+pub struct TableSummary {
+    pub table_number: u32,
+    pub items: u32,
+    /// Items not served yet.
+    pub pending: u32,
+    pub first_ordered_on: DateTime<Utc>,
+}
+
+pub fn list_tables(pool: &Pool) -> Result<Vec<TableSummary>, PersistenceError> {
+    let mut conn = get_conn(pool, "list_tables")?;
+    let rows: Vec<(u32, u32, u32, NaiveDateTime)> = conn.query(generate_tables_query()).map_err(|e| {
+        error!(error = ?e, "Listing tables failed");
+        PersistenceError::DBOpError
+    })?;
+    Ok(rows.into_iter().map(|(table_number, items, pending, first_ordered_on)| TableSummary {
+        table_number,
+        items,
+        pending,
+        first_ordered_on: first_ordered_on.and_utc(),
+    }).collect())
+}
+
+/// Deletes the items ordered before `before`, voided or not, and returns how many there were.
+/// Their audit entries are kept and the run is audited as a `purge` in the same transaction.
+/// With `dry_run` the items are only counted.
+pub fn purge_items(pool: &Pool, before: DateTime<Utc>, dry_run: bool) -> Result<u64, PersistenceError> {
+    let mut conn = get_conn(pool, "purge_items")?;
+    if dry_run {
+        return conn.exec_first::<u64, _, _>("SELECT COUNT(*) FROM table_items WHERE ordered_on < ?", (before.naive_utc(),))
+            .map(Option::unwrap_or_default)
+            .map_err(|e| {
+                error!(error = ?e, "Counting items to purge failed");
+                PersistenceError::DBOpError
+            });
+    }
+
+    in_transaction(&mut conn, |tx| {
+        tx.exec_iter("DELETE FROM table_items WHERE ordered_on < ?", (before.naive_utc(),))
+            .map(|result| result.affected_rows())
+            .and_then(|purged| record_purge_entry(tx, before, purged).map(|_| purged))
+            .map_err(|e| {
+                error!(error = ?e, "Purging items failed");
+                PersistenceError::DBOpError
+            })
+    })
+}
+
+/// All items, voided ones included, optionally of one table, in `item_id` order.
+pub fn export_items(pool: &Pool, table_number: Option<u32>) -> Result<Vec<TableItem>, PersistenceError> {
+    let (query, params) = generate_export_query_and_params(table_number);
+    let mut conn = get_conn(pool, "export_items")?;
+    let result = match conn.exec_iter(query, params) {
+        Ok(result) => result
+            .map(convert_row_to_table_item)
+            .collect::<Result<Vec<TableItem>, mysql::Error>>()
+            .map_err(|e| {
+                error!(error = ?e, "Reading exported items failed");
+                PersistenceError::DBOpError
+            }),
+        Err(e) => {
+            error!(error = ?e, "Exporting items failed");
+            Err(PersistenceError::DBOpError)
+        }
+    };
+    result
+}
+
+fn generate_tables_query() -> String {
+    "SELECT table_number, COUNT(*), CAST(SUM(status <> 'served') AS UNSIGNED), MIN(ordered_on) \
+     FROM table_items WHERE voided_on IS NULL GROUP BY table_number ORDER BY table_number".to_string()
+}
+
+fn generate_export_query_and_params(table_number: Option<u32>) -> (String, Vec<Value>) {
+    match table_number {
+        Some(table_number) => (
+            "SELECT * FROM table_items WHERE table_number = ? ORDER BY item_id".to_string(),
+            vec![table_number.into()],
+        ),
+        None => ("SELECT * FROM table_items ORDER BY item_id".to_string(), vec![]),
+    }
+}
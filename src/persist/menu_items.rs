@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use tracing::error;
+use mysql::prelude::*;
+use mysql::{Pool, Value};
+
+use crate::common::errors::PersistenceError;
+use crate::model::resources::MenuItem;
+use crate::persist::connection::get_conn;
+
+/// Adds the items to the menu, replacing the preparation time of the ones already on it.
+pub fn upsert_menu_items(pool: &Pool, items: &[MenuItem]) -> Result<(), PersistenceError> {
+    let updated_on = Utc::now().naive_utc();
+    let params = items.iter().flat_map(|item| {
+        vec![item.item_name.as_str().into(), item.prepare_minutes.into(), updated_on.into()]
+    }).collect::<Vec<Value>>();
+
+    let mut conn = get_conn(pool, "upsert_menu_items")?;
+    conn.exec_drop(generate_upsert_query(items.len()), params).map_err(|e| {
+        error!(error = ?e, "Seeding the menu failed");
+        PersistenceError::DBOpError
+    })
+}
+
+/// Names of all the dishes on the menu.
+pub fn get_menu_names(pool: &Pool) -> Result<Vec<String>, PersistenceError> {
+    let mut conn = get_conn(pool, "get_menu_names")?;
+    conn.query("SELECT item_name FROM menu_items ORDER BY item_name").map_err(|e| {
+        error!(error = ?e, "Reading the menu failed");
+        PersistenceError::DBOpError
+    })
+}
+
+/// Preparation times of the named items found on the menu, keyed by lowercase name as the
+/// column's collation compares names case-insensitively.
+pub(crate) fn get_prepare_minutes<Q: Queryable>(
+    conn: &mut Q,
+    items_names: &[String],
+) -> Result<HashMap<String, u32>, mysql::Error> {
+    if items_names.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let placeholders = vec!["?"; items_names.len()].join(",");
+    let query = format!("SELECT item_name, prepare_minutes FROM menu_items WHERE item_name IN ({placeholders})");
+    let rows: Vec<(String, u32)> = conn.exec(query, items_names.to_vec())?;
+    Ok(rows.into_iter().map(|(item_name, prepare_minutes)| (item_name.to_lowercase(), prepare_minutes)).collect())
+}
+
+fn generate_upsert_query(num_items: usize) -> String {
+    let placeholders = vec!["(?, ?, ?)"; num_items].join(", ");
+    format!(
+        "INSERT INTO menu_items (item_name, prepare_minutes, updated_on) VALUES {placeholders} AS new \
+         ON DUPLICATE KEY UPDATE prepare_minutes = new.prepare_minutes, updated_on = new.updated_on"
+    )
+}
@@ -0,0 +1,247 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use chrono_tz::Tz;
+use tracing::{error, info};
+use mysql::prelude::*;
+use mysql::{Pool, PooledConn};
+
+use crate::common::errors::PersistenceError;
+use crate::model::time::RestaurantTimezone;
+use crate::persist::connection::get_conn;
+
+/// Schema change shipped with the binary. Versions are applied in ascending order and never edited
+/// once released; a change to the schema is a new migration.
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    up: &'static str,
+    down: &'static str,
+}
+
+/// Embeds `migrations/<name>.up.sql` and `migrations/<name>.down.sql`.
+macro_rules! migration {
+    ($version:literal, $name:literal) => {
+        Migration {
+            version: $version,
+            name: $name,
+            up: include_str!(concat!("../../migrations/", $name, ".up.sql")),
+            down: include_str!(concat!("../../migrations/", $name, ".down.sql")),
+        }
+    };
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    migration!(1, "0001_initial_schema"),
+    migration!(2, "0002_audit_log"),
+    migration!(3, "0003_void_items"),
+    migration!(4, "0004_idempotency_keys"),
+    migration!(5, "0005_item_versions"),
+    migration!(6, "0006_item_status"),
+    migration!(7, "0007_utc_timestamps"),
+    migration!(8, "0008_menu_items"),
+    migration!(9, "0009_idempotency_keys_created_on"),
+];
+
+/// Session variable holding the MySQL name of the restaurant's timezone, for migrations converting
+/// timestamps stored in local time.
+const TIMEZONE_VARIABLE: &str = "@restaurant_timezone";
+
+const ER_NO_SUCH_TABLE: u16 = 1146;
+
+pub struct MigrationStatus {
+    pub migration: &'static Migration,
+    pub applied_on: Option<DateTime<Utc>>,
+}
+
+/// How the database schema relates to the migrations of this binary.
+pub enum SchemaState {
+    UpToDate,
+    /// Migrations of this binary are not applied yet.
+    Outdated { current: u32, expected: u32 },
+    /// The database was migrated by a newer binary.
+    Ahead { current: u32, expected: u32 },
+    /// `schema_migrations` does not exist: no migration ever ran.
+    Uninitialized { expected: u32 },
+}
+
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map(|migration| migration.version).unwrap_or_default()
+}
+
+/// Applies the pending migrations in order and returns them. MySQL commits DDL implicitly, so a
+/// failing migration is not recorded and may leave part of its statements applied.
+pub fn migrate_up(pool: &Pool, timezone: RestaurantTimezone) -> Result<Vec<&'static Migration>, PersistenceError> {
+    let mut conn = get_conn(pool, "migrate_up")?;
+    let mut applied = applied_versions(&mut conn)?;
+    if applied.is_empty() && has_baseline_schema(&mut conn)? {
+        adopt_baseline_schema(&mut conn)?;
+        applied.push(MIGRATIONS[0].version);
+    }
+
+    let pending: Vec<&'static Migration> = MIGRATIONS.iter()
+        .filter(|migration| !applied.contains(&migration.version))
+        .collect();
+    if pending.iter().any(|migration| migration.up.contains(TIMEZONE_VARIABLE)) {
+        set_restaurant_timezone(&mut conn, timezone)?;
+    }
+
+    let mut migrated = Vec::new();
+    for migration in pending {
+        info!(migration = migration.name, "Applying migration");
+        conn.query_drop(migration.up)
+            .and_then(|_| conn.exec_drop(
+                "INSERT INTO schema_migrations (version, name, applied_on) VALUES (?, ?, ?)",
+                (migration.version, migration.name, Utc::now().naive_utc(),),
+            ))
+            .map_err(|e| {
+                error!(migration = migration.name, error = ?e, "Migration failed");
+                PersistenceError::DBOpError
+            })?;
+        migrated.push(migration);
+    }
+    Ok(migrated)
+}
+
+/// Reverts the latest applied migration, if any.
+pub fn migrate_down(pool: &Pool, timezone: RestaurantTimezone) -> Result<Option<&'static Migration>, PersistenceError> {
+    let mut conn = get_conn(pool, "migrate_down")?;
+    let applied = applied_versions(&mut conn)?;
+    let Some(&version) = applied.iter().max() else {
+        return Ok(None);
+    };
+    let migration = MIGRATIONS.iter().find(|migration| migration.version == version).ok_or_else(|| {
+        error!(version, "Migration is applied but unknown to this binary");
+        PersistenceError::ResourceNotFound
+    })?;
+
+    if migration.down.contains(TIMEZONE_VARIABLE) {
+        set_restaurant_timezone(&mut conn, timezone)?;
+    }
+
+    info!(migration = migration.name, "Reverting migration");
+    conn.query_drop(migration.down)
+        .and_then(|_| conn.exec_drop("DELETE FROM schema_migrations WHERE version = ?", (migration.version,)))
+        .map_err(|e| {
+            error!(migration = migration.name, error = ?e, "Reverting migration failed");
+            PersistenceError::DBOpError
+        })?;
+    Ok(Some(migration))
+}
+
+pub fn migration_status(pool: &Pool) -> Result<Vec<MigrationStatus>, PersistenceError> {
+    let mut conn = get_conn(pool, "migration_status")?;
+    create_migrations_table(&mut conn)?;
+    let applied: Vec<(u32, NaiveDateTime)> = conn.query("SELECT version, applied_on FROM schema_migrations")
+        .map_err(|e| {
+            error!(error = ?e, "Reading applied migrations failed");
+            PersistenceError::DBOpError
+        })?;
+
+    Ok(MIGRATIONS.iter().map(|migration| MigrationStatus {
+        migration,
+        applied_on: applied.iter()
+            .find(|(version, _)| *version == migration.version)
+            .map(|(_, applied_on)| applied_on.and_utc()),
+    }).collect())
+}
+
+/// Checked on startup: serving against a schema missing migrations would fail at query time.
+pub fn check_schema(pool: &Pool) -> Result<SchemaState, PersistenceError> {
+    let mut conn = get_conn(pool, "check_schema")?;
+    schema_state(&mut conn)
+}
+
+/// Read-only, so it can back the readiness probe: unlike `migrate`, it never creates `schema_migrations`.
+pub(crate) fn schema_state(conn: &mut PooledConn) -> Result<SchemaState, PersistenceError> {
+    let expected = latest_version();
+    let applied: Vec<u32> = match conn.query("SELECT version FROM schema_migrations ORDER BY version") {
+        Ok(applied) => applied,
+        Err(mysql::Error::MySqlError(e)) if e.code == ER_NO_SUCH_TABLE => {
+            return Ok(SchemaState::Uninitialized { expected });
+        }
+        Err(e) => {
+            error!(error = ?e, "Reading applied migrations failed");
+            return Err(PersistenceError::DBOpError);
+        }
+    };
+    let current = applied.iter().copied().max().unwrap_or_default();
+
+    Ok(if MIGRATIONS.iter().any(|migration| !applied.contains(&migration.version)) {
+        SchemaState::Outdated { current, expected }
+    } else if current > expected {
+        SchemaState::Ahead { current, expected }
+    } else {
+        SchemaState::UpToDate
+    })
+}
+
+fn applied_versions(conn: &mut PooledConn) -> Result<Vec<u32>, PersistenceError> {
+    create_migrations_table(conn)?;
+    conn.query("SELECT version FROM schema_migrations ORDER BY version").map_err(|e| {
+        error!(error = ?e, "Reading applied migrations failed");
+        PersistenceError::DBOpError
+    })
+}
+
+/// Databases created by the `mysql_setup.sql` script predating the migrations already have the
+/// `0001_initial_schema` tables but no `schema_migrations` rows.
+fn has_baseline_schema(conn: &mut PooledConn) -> Result<bool, PersistenceError> {
+    conn.query_first::<bool, _>(
+        "SELECT EXISTS(SELECT 1 FROM information_schema.tables \
+         WHERE table_schema = DATABASE() AND table_name = 'table_items')"
+    )
+        .map(|exists| exists.unwrap_or(false))
+        .map_err(|e| {
+            error!(error = ?e, "Looking for an existing schema failed");
+            PersistenceError::DBOpError
+        })
+}
+
+fn adopt_baseline_schema(conn: &mut PooledConn) -> Result<(), PersistenceError> {
+    let baseline = &MIGRATIONS[0];
+    info!(migration = baseline.name, "Recording the existing schema as migrated");
+    conn.exec_drop(
+        "INSERT INTO schema_migrations (version, name, applied_on) VALUES (?, ?, ?)",
+        (baseline.version, baseline.name, Utc::now().naive_utc(),),
+    ).map_err(|e| {
+        error!(migration = baseline.name, error = ?e, "Recording the existing schema failed");
+        PersistenceError::DBOpError
+    })
+}
+
+/// Sets `TIMEZONE_VARIABLE` and checks MySQL can convert from it: `CONVERT_TZ` answers NULL for
+/// names missing from its time zone tables, which would silently null the converted timestamps.
+fn set_restaurant_timezone(conn: &mut PooledConn, timezone: RestaurantTimezone) -> Result<(), PersistenceError> {
+    let name = match timezone.0 {
+        // Always known to MySQL, even without its time zone tables loaded.
+        Tz::UTC | Tz::Etc__UTC => "+00:00",
+        tz => tz.name(),
+    };
+    let converted = conn.exec_drop(format!("SET {TIMEZONE_VARIABLE} = ?"), (name,))
+        .and_then(|_| conn.query_first::<Option<NaiveDateTime>, _>(
+            format!("SELECT CONVERT_TZ('2024-01-01 00:00:00', {TIMEZONE_VARIABLE}, '+00:00')")
+        ))
+        .map_err(|e| {
+            error!(error = ?e, "Setting the restaurant timezone failed");
+            PersistenceError::DBOpError
+        })?;
+    if converted.flatten().is_none() {
+        error!(timezone = name, "MySQL does not know the restaurant timezone; load its time zone tables with mysql_tzinfo_to_sql first");
+        return Err(PersistenceError::DBOpError);
+    }
+    Ok(())
+}
+
+fn create_migrations_table(conn: &mut PooledConn) -> Result<(), PersistenceError> {
+    conn.query_drop(generate_migrations_table_query()).map_err(|e| {
+        error!(error = ?e, "Creating schema_migrations failed");
+        PersistenceError::DBOpError
+    })
+}
+
+fn generate_migrations_table_query() -> String {
+    "CREATE TABLE IF NOT EXISTS schema_migrations (
+       version INT UNSIGNED PRIMARY KEY NOT NULL,
+       name VARCHAR(127) NOT NULL,
+       applied_on DATETIME(6) NOT NULL COMMENT 'UTC'
+     ) ENGINE = InnoDB CHARACTER SET = utf8mb4 COLLATE = utf8mb4_0900_ai_ci".to_string()
+}
@@ -1,3 +1,13 @@
+pub mod audit_log;
+pub mod bulk_table_items;
+pub mod connection;
 pub mod fetch_table_items;
+pub mod health;
+pub mod idempotency_keys;
+pub mod maintenance;
+pub mod menu_items;
+pub mod migrations;
 pub mod persist_table_items;
-pub mod remove_table_items;
\ No newline at end of file
+pub mod retry;
+pub mod transaction;
+pub mod void_table_items;
\ No newline at end of file
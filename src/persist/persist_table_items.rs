@@ -1,55 +1,152 @@
 use actix_request_identifier::RequestId;
-use chrono::Local;
-use log::{debug, error};
+use std::collections::HashMap;
+
+use chrono::{DateTime, SubsecRound, Utc};
+use tracing::{error, instrument, warn};
 use mysql::prelude::*;
-use mysql::{Pool};
+use mysql::Pool;
 use rand::Rng;
+use crate::common::actor::Actor;
 use crate::common::errors::PersistenceError;
+use crate::common::metrics::METRICS;
+use crate::model::resources::{AuditAction, ItemStatus, TableItem};
 use crate::model::responses::AddItemsResponse;
+use crate::persist::connection::get_conn;
+use crate::persist::audit_log::{record_audit_entries, AuditRecord};
+use crate::persist::idempotency_keys::{
+    claim_idempotency_key, store_idempotent_response, sweep_expired_idempotency_keys, IdempotencyClaim, IdempotencyKey,
+};
+use crate::persist::menu_items::get_prepare_minutes;
+use crate::persist::retry::{op_error, retry_transient};
+use crate::persist::transaction::{in_transaction, Transaction};
+
+pub enum AddItemsOutcome {
+    Created(AddItemsResponse),
+    /// Response stored for an earlier request carrying the same `Idempotency-Key`.
+    Replayed(AddItemsResponse),
+}
 
+#[instrument(skip_all, err, fields(request_id = %request_id, table_number = table_number, items_names = ?items_names))]
 pub fn add_items_to_table(
     pool: &Pool,
     request_id: RequestId,
+    actor: Actor,
     table_number: u32,
     items_names: Vec<String>,
-) -> Result<AddItemsResponse, PersistenceError> {
-    let records: Vec<(String, String, String)> = generate_table_item_records(items_names);
-    let query = generate_query(records.len());
+    idempotency_key: Option<IdempotencyKey>,
+) -> Result<AddItemsOutcome, PersistenceError> {
+    let request_fingerprint = generate_request_fingerprint(table_number, &items_names);
+
+    let mut conn = get_conn(pool, "add_items_to_table")?;
+    let menu = get_prepare_minutes(&mut *conn, &items_names).map_err(|e| {
+        error!(error = ?e, "Reading the menu failed");
+        PersistenceError::DBOpError
+    })?;
+    let records: Vec<(String, DateTime<Utc>, u32)> = generate_table_item_records(items_names, &menu);
 
-    let mut conn = pool.get_conn().map_err(|_| PersistenceError::DBConnError)?;
-    conn.query_drop("START TRANSACTION").map_err(|_| PersistenceError::TransactionStartError)?;
+    // Autocommitted on its own so the claim's transaction never locks other clients' expired keys.
+    if let Some(ref idempotency_key) = idempotency_key {
+        if let Err(e) = sweep_expired_idempotency_keys(&mut *conn, idempotency_key.ttl_seconds) {
+            warn!(error = ?e, "Sweeping expired idempotency keys failed");
+        }
+    }
 
+    let outcome = retry_transient("add_items_to_table", || in_transaction(&mut conn, |tx| match idempotency_key {
+        Some(ref idempotency_key) => claim_idempotency_key(tx, idempotency_key, &request_fingerprint)
+            .map_err(|e| {
+                error!(error = ?e, "Claiming idempotency key failed");
+                op_error(&e)
+            })
+            .and_then(|claim| match claim {
+                IdempotencyClaim::Claimed => insert_items(tx, &request_id, &actor, table_number, &records)
+                    .and_then(|response| store_response(tx, idempotency_key, response)),
+                IdempotencyClaim::Replay(response_body) => serde_json::from_str(&response_body)
+                    .map(AddItemsOutcome::Replayed)
+                    .map_err(|e| {
+                        error!(error = ?e, "Stored idempotent response is corrupted");
+                        PersistenceError::DBOpError
+                    }),
+                IdempotencyClaim::Mismatch => {
+                    error!("Idempotency key reused with a different request");
+                    Err(PersistenceError::IdempotencyKeyMismatch)
+                }
+            }),
+        None => insert_items(tx, &request_id, &actor, table_number, &records)
+            .map(AddItemsOutcome::Created),
+    }))?;
+
+    if let AddItemsOutcome::Created(_) = outcome {
+        records.iter().for_each(|(item_name, _, _)| METRICS.item_ordered(item_name));
+    }
+    Ok(outcome)
+}
+
+fn insert_items(
+    conn: &mut Transaction,
+    request_id: &RequestId,
+    actor: &Actor,
+    table_number: u32,
+    records: &[(String, DateTime<Utc>, u32)],
+) -> Result<AddItemsResponse, PersistenceError> {
+    let query = generate_query(records.len());
     let params = records.iter().flat_map(|(item_name, ordered_on, prepare_minutes)| {
         vec![
             table_number.into(),
             item_name.into(),
-            ordered_on.into(),
+            ordered_on.naive_utc().into(),
             prepare_minutes.into(),
         ]
     }).collect::<Vec<mysql::Value>>();
 
-    match conn.exec_drop(query, params) {
-        Ok(_) => {
-            let last_id = conn.query_first::<u32, _>("SELECT LAST_INSERT_ID()")
-                .unwrap_or(Some(0)).expect("Error: Unable to get the inserted id");
-            let item_ids: Vec<u32> = (last_id..last_id + records.len() as u32).collect();
-            conn.query_drop("COMMIT").map_err(|_| PersistenceError::CommitError)?;
-            Ok(generate_success_response(table_number, records.len(), item_ids))
-        }
-        Err(e) => {
-            conn.query_drop("ROLLBACK").map_err(|_| PersistenceError::RollbackError)?;
-            error!("{:?}", e);
-            Ok(generate_failed_response(table_number))
-        }
-    }
+    conn.exec_drop(query, params).map_err(|e| {
+        error!(error = ?e, "Adding items failed");
+        op_error(&e)
+    })?;
+
+    let last_id = conn.query_first::<u32, _>("SELECT LAST_INSERT_ID()")
+        .map_err(|e| {
+            error!(error = ?e, "Reading the inserted ids failed");
+            op_error(&e)
+        })?
+        .ok_or_else(|| {
+            error!("No inserted id returned");
+            PersistenceError::DBOpError
+        })?;
+    let item_ids: Vec<u32> = (last_id..last_id + records.len() as u32).collect();
+
+    let added_items = generate_added_items(table_number, records, &item_ids);
+    let audit_records = added_items.iter().map(|item| AuditRecord {
+        action: AuditAction::Add,
+        table_number,
+        item_id: item.item_id,
+        before: None,
+        after: Some(item),
+    }).collect::<Vec<AuditRecord>>();
+    record_audit_entries(conn, request_id, actor, &audit_records).map_err(|e| {
+        error!(error = ?e, "Audit recording failed");
+        op_error(&e)
+    })?;
+
+    Ok(generate_success_response(table_number, records.len(), item_ids))
 }
 
-fn generate_failed_response(table_number: u32) -> AddItemsResponse {
-    AddItemsResponse {
-        status: "failed".to_string(),
-        message: format!("Can NOT add item(s) to table {}", table_number),
-        items_ids: [].to_vec(),
-    }
+fn store_response(
+    conn: &mut Transaction,
+    idempotency_key: &IdempotencyKey,
+    response: AddItemsResponse,
+) -> Result<AddItemsOutcome, PersistenceError> {
+    serde_json::to_string(&response)
+        .map_err(|e| mysql::Error::from(std::io::Error::other(e)))
+        .and_then(|response_body| store_idempotent_response(conn, idempotency_key, &response_body))
+        .map_err(|e| {
+            error!(error = ?e, "Storing idempotent response failed");
+            op_error(&e)
+        })?;
+    Ok(AddItemsOutcome::Created(response))
+}
+
+fn generate_request_fingerprint(table_number: u32, items_names: &[String]) -> String {
+    serde_json::json!({ "table_number": table_number, "items_names": items_names }).to_string()
 }
 
 fn generate_success_response(table_number: u32, num_items: usize, item_ids: Vec<u32>) -> AddItemsResponse {
@@ -60,6 +157,20 @@ fn generate_success_response(table_number: u32, num_items: usize, item_ids: Vec<
     }
 }
 
+fn generate_added_items(table_number: u32, records: &[(String, DateTime<Utc>, u32)], item_ids: &[u32]) -> Vec<TableItem> {
+    records.iter().zip(item_ids).map(|((item_name, ordered_on, prepare_minutes), &item_id)| TableItem {
+        item_id,
+        table_number,
+        item_name: item_name.clone(),
+        ordered_on: *ordered_on,
+        prepare_minutes: *prepare_minutes,
+        voided_on: None,
+        void_reason: None,
+        version: 1,
+        status: ItemStatus::Ordered,
+    }).collect()
+}
+
 fn generate_query(num_records: usize) -> String {
     let placeholders: Vec<String> = (0..num_records)
         .map(|_| "(?, ?, ?, ?)".to_string())
@@ -70,13 +181,13 @@ fn generate_query(num_records: usize) -> String {
     )
 }
 
-fn generate_table_item_records(items_names: Vec<String>) -> Vec<(String, String, String)> {
-    items_names.into_iter().filter_map(|item_name| {
-        if item_name.replace(' ', "").trim().is_empty() {
-            return None;
-        }
-        let ordered_on = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
-        let prepare_minutes = rand::thread_rng().gen_range(5..16).to_string();
-        Some((item_name, ordered_on, prepare_minutes))
+/// Items missing from the menu get a random preparation time.
+fn generate_table_item_records(items_names: Vec<String>, menu: &HashMap<String, u32>) -> Vec<(String, DateTime<Utc>, u32)> {
+    items_names.into_iter().map(|item_name| {
+        // DATETIME(6) keeps microseconds; truncating keeps the returned item equal to the stored one.
+        let ordered_on = Utc::now().trunc_subsecs(6);
+        let prepare_minutes = menu.get(&item_name.to_lowercase()).copied()
+            .unwrap_or_else(|| rand::thread_rng().gen_range(5..16));
+        (item_name, ordered_on, prepare_minutes)
     }).collect()
 }
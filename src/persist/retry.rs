@@ -0,0 +1,64 @@
+use std::thread;
+use std::time::Duration;
+
+use rand::Rng;
+use tracing::warn;
+
+use crate::common::errors::PersistenceError;
+use crate::common::metrics::METRICS;
+
+/// First run included.
+const MAX_ATTEMPTS: u32 = 3;
+const BASE_BACKOFF: Duration = Duration::from_millis(20);
+
+const ER_LOCK_WAIT_TIMEOUT: u16 = 1205;
+const ER_LOCK_DEADLOCK: u16 = 1213;
+
+/// `DBOpError`, unless MySQL reported a deadlock or a lock wait timeout: those only depend on
+/// what ran concurrently, so the transaction is worth running again.
+pub(crate) fn op_error(e: &mysql::Error) -> PersistenceError {
+    match e {
+        mysql::Error::MySqlError(e) if e.code == ER_LOCK_DEADLOCK => PersistenceError::Deadlock,
+        mysql::Error::MySqlError(e) if e.code == ER_LOCK_WAIT_TIMEOUT => PersistenceError::LockWaitTimeout,
+        _ => PersistenceError::DBOpError,
+    }
+}
+
+/// Runs `transaction` again after a deadlock or a lock wait timeout, up to `MAX_ATTEMPTS` times,
+/// sleeping a random part of an exponential backoff in between so the contenders do not collide again.
+/// `transaction` must start its transaction and roll it back on errors itself.
+pub(crate) fn retry_transient<T>(
+    function: &'static str,
+    mut transaction: impl FnMut() -> Result<T, PersistenceError>,
+) -> Result<T, PersistenceError> {
+    let mut attempt = 1;
+    loop {
+        let e = match transaction() {
+            Ok(value) => return Ok(value),
+            Err(e) => e,
+        };
+        let Some(label) = transient_label(&e) else {
+            return Err(e);
+        };
+        if attempt == MAX_ATTEMPTS {
+            METRICS.db_retries_exhausted.with_label_values(&[function, label]).inc();
+            warn!(function, attempts = attempt, error = label, "Transaction still failing, giving up");
+            return Err(e);
+        }
+
+        let backoff = BASE_BACKOFF * 2u32.pow(attempt - 1);
+        let backoff = rand::thread_rng().gen_range(backoff / 2..=backoff);
+        METRICS.db_retries.with_label_values(&[function, label]).inc();
+        warn!(function, attempt, error = label, backoff_ms = backoff.as_millis() as u64, "Transaction failed, retrying");
+        thread::sleep(backoff);
+        attempt += 1;
+    }
+}
+
+fn transient_label(e: &PersistenceError) -> Option<&'static str> {
+    match e {
+        PersistenceError::Deadlock => Some("deadlock"),
+        PersistenceError::LockWaitTimeout => Some("lock_wait_timeout"),
+        _ => None,
+    }
+}
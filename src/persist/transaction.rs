@@ -0,0 +1,182 @@
+use mysql::prelude::{AsStatement, Queryable};
+use mysql::{Binary, Params, QueryResult, Statement, Text, TxOpts};
+use tracing::error;
+
+use crate::common::errors::PersistenceError;
+use crate::common::metrics::METRICS;
+use crate::persist::connection::Connection;
+
+const COMMITTED: &str = "committed";
+const ROLLED_BACK: &str = "rolled_back";
+/// Rolled back by the driver when dropped, e.g. on a panic or a failed commit.
+const DROPPED: &str = "dropped";
+
+/// Transaction on a connection checked out with `get_conn`. Dropped without `commit` or
+/// `rollback`, it is rolled back, so the connection never goes back to the pool mid-transaction.
+pub(crate) struct Transaction<'a> {
+    tx: mysql::Transaction<'a>,
+    open: OpenTransaction,
+}
+
+/// Counted as open until dropped, then by what ended it.
+struct OpenTransaction {
+    function: &'static str,
+    outcome: &'static str,
+}
+
+impl<'a> Transaction<'a> {
+    pub(crate) fn begin(conn: &'a mut Connection) -> Result<Self, PersistenceError> {
+        let function = conn.function;
+        let tx = conn.start_transaction(TxOpts::default()).map_err(|e| {
+            error!(error = ?e, "Can NOT begin the transaction");
+            PersistenceError::TransactionStartError
+        })?;
+        METRICS.db_open_transactions.inc();
+        Ok(Transaction { tx, open: OpenTransaction { function, outcome: DROPPED } })
+    }
+
+    pub(crate) fn commit(self) -> Result<(), PersistenceError> {
+        let Transaction { tx, mut open } = self;
+        tx.commit().map_err(|e| {
+            error!(error = ?e, "Can NOT commit the transaction");
+            PersistenceError::CommitError
+        })?;
+        open.outcome = COMMITTED;
+        Ok(())
+    }
+
+    pub(crate) fn rollback(self) -> Result<(), PersistenceError> {
+        let Transaction { tx, mut open } = self;
+        tx.rollback().map_err(|e| {
+            error!(error = ?e, "Can NOT rollback the transaction");
+            PersistenceError::RollbackError
+        })?;
+        open.outcome = ROLLED_BACK;
+        Ok(())
+    }
+}
+
+/// Runs `f` in a transaction committed when it succeeds and rolled back when it fails,
+/// returns early or panics.
+pub(crate) fn in_transaction<T>(
+    conn: &mut Connection,
+    f: impl FnOnce(&mut Transaction) -> Result<T, PersistenceError>,
+) -> Result<T, PersistenceError> {
+    let mut tx = Transaction::begin(conn)?;
+    match f(&mut tx) {
+        Ok(value) => tx.commit().map(|_| value),
+        Err(e) => {
+            tx.rollback()?;
+            Err(e)
+        }
+    }
+}
+
+impl Drop for OpenTransaction {
+    fn drop(&mut self) {
+        METRICS.db_open_transactions.dec();
+        METRICS.db_transactions.with_label_values(&[self.function, self.outcome]).inc();
+    }
+}
+
+impl Queryable for Transaction<'_> {
+    fn query_iter<T: AsRef<str>>(&mut self, query: T) -> mysql::Result<QueryResult<'_, '_, '_, Text>> {
+        self.tx.query_iter(query)
+    }
+
+    fn prep<T: AsRef<str>>(&mut self, query: T) -> mysql::Result<Statement> {
+        self.tx.prep(query)
+    }
+
+    fn close(&mut self, stmt: Statement) -> mysql::Result<()> {
+        self.tx.close(stmt)
+    }
+
+    fn exec_iter<S, P>(&mut self, stmt: S, params: P) -> mysql::Result<QueryResult<'_, '_, '_, Binary>>
+    where
+        S: AsStatement,
+        P: Into<Params>,
+    {
+        self.tx.exec_iter(stmt, params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    use mysql::prelude::Queryable;
+    use mysql::Pool;
+
+    use super::{in_transaction, Transaction};
+    use crate::common::errors::PersistenceError;
+    use crate::config::Config;
+    use crate::persist::connection::{connect, get_conn};
+
+    /// Far from the tables the hurl tests use; nothing is ever committed to it.
+    const TABLE_NUMBER: u32 = 4_000_000;
+
+    fn pool() -> Pool {
+        dotenvy::dotenv().ok();
+        let config = Config::load(None).expect("invalid configuration");
+        connect(&config.database).expect("MySQL is not reachable")
+    }
+
+    fn insert_item(tx: &mut Transaction) -> Result<(), PersistenceError> {
+        tx.exec_drop(
+            "INSERT INTO table_items (table_number, item_name, prepare_minutes, ordered_on) \
+             VALUES (?, 'Soup', 5, UTC_TIMESTAMP(6))",
+            (TABLE_NUMBER,),
+        ).map_err(|_| PersistenceError::DBOpError)
+    }
+
+    /// Asked from another connection, so the check itself runs outside of the transaction.
+    fn has_open_transaction(pool: &Pool, connection_id: u32) -> bool {
+        pool.get_conn().unwrap()
+            .exec_first::<bool, _, _>(
+                "SELECT EXISTS(SELECT 1 FROM information_schema.innodb_trx WHERE trx_mysql_thread_id = ?)",
+                (connection_id,),
+            )
+            .unwrap()
+            .unwrap_or(true)
+    }
+
+    fn count_items(pool: &Pool) -> u64 {
+        pool.get_conn().unwrap()
+            .exec_first("SELECT COUNT(*) FROM table_items WHERE table_number = ?", (TABLE_NUMBER,))
+            .unwrap()
+            .unwrap_or_default()
+    }
+
+    #[test]
+    #[ignore = "needs the MySQL configured in .env"]
+    fn rolls_back_when_returning_early() {
+        let pool = pool();
+        let mut conn = get_conn(&pool, "rolls_back_when_returning_early").unwrap();
+
+        let result = in_transaction(&mut conn, |tx| {
+            insert_item(tx)?;
+            Err::<(), _>(PersistenceError::ResourceConflict)
+        });
+
+        assert!(matches!(result, Err(PersistenceError::ResourceConflict)));
+        assert!(!has_open_transaction(&pool, conn.connection_id()));
+        assert_eq!(count_items(&pool), 0);
+    }
+
+    #[test]
+    #[ignore = "needs the MySQL configured in .env"]
+    fn rolls_back_when_panicking() {
+        let pool = pool();
+        let mut conn = get_conn(&pool, "rolls_back_when_panicking").unwrap();
+
+        let result = catch_unwind(AssertUnwindSafe(|| in_transaction(&mut conn, |tx| -> Result<(), PersistenceError> {
+            insert_item(tx)?;
+            panic!("failing after the insert")
+        })));
+
+        assert!(result.is_err());
+        assert!(!has_open_transaction(&pool, conn.connection_id()));
+        assert_eq!(count_items(&pool), 0);
+    }
+}
@@ -0,0 +1,144 @@
+use actix_request_identifier::RequestId;
+use chrono::{SubsecRound, Utc};
+use tracing::{error, instrument};
+use mysql::prelude::*;
+use mysql::Pool;
+use crate::common::actor::Actor;
+use crate::common::etag::Precondition;
+use crate::common::errors::PersistenceError;
+use crate::common::metrics::METRICS;
+use crate::model::resources::{AuditAction, TableItem, VoidReason};
+use crate::model::responses::{RemoveTableItemResponse};
+use crate::persist::connection::get_conn;
+use crate::persist::audit_log::{record_audit_entries, AuditRecord};
+use crate::persist::fetch_table_items::select_item_for_update;
+use crate::persist::retry::{op_error, retry_transient};
+use crate::persist::transaction::{in_transaction, Transaction};
+
+/// Voids the item instead of deleting it: the row stays for accounting and waste
+/// tracking but is excluded from the table listings until restored.
+#[instrument(skip_all, err, fields(request_id = %request_id, table_number = table_number, item_id = item_id))]
+pub fn void_table_item(
+    pool: &Pool,
+    request_id: RequestId,
+    actor: Actor,
+    table_number: u32,
+    item_id: u32,
+    reason: VoidReason,
+    precondition: Precondition,
+) -> Result<(RemoveTableItemResponse, TableItem), PersistenceError> {
+    let mut conn = get_conn(pool, "void_table_item")?;
+
+    let voided = retry_transient("void_table_item", || in_transaction(&mut conn, |tx| {
+        void_item(tx, &request_id, &actor, table_number, item_id, reason, &precondition)
+    }))?;
+    METRICS.item_removed(&voided.item_name, reason);
+    Ok((generate_success_response(item_id), voided))
+}
+
+/// Brings a voided item back into the table listings.
+#[instrument(skip_all, err, fields(request_id = %request_id, table_number = table_number, item_id = item_id))]
+pub fn restore_table_item(
+    pool: &Pool,
+    request_id: RequestId,
+    actor: Actor,
+    table_number: u32,
+    item_id: u32,
+    precondition: Precondition,
+) -> Result<TableItem, PersistenceError> {
+    let mut conn = get_conn(pool, "restore_table_item")?;
+
+    retry_transient("restore_table_item", || in_transaction(&mut conn, |tx| {
+        restore_item(tx, &request_id, &actor, table_number, item_id, &precondition)
+    }))
+}
+
+#[instrument(skip_all, err, fields(item_id = item_id))]
+pub(crate) fn void_item(
+    conn: &mut Transaction,
+    request_id: &RequestId,
+    actor: &Actor,
+    table_number: u32,
+    item_id: u32,
+    reason: VoidReason,
+    precondition: &Precondition,
+) -> Result<TableItem, PersistenceError> {
+    let before = select_item_for_update(conn, table_number, item_id, precondition)?;
+    if before.voided_on.is_some() {
+        error!(item_id, "Item is already voided");
+        return Err(PersistenceError::ResourceConflict);
+    }
+
+    let voided_on = Utc::now().trunc_subsecs(6);
+    let after = TableItem {
+        voided_on: Some(voided_on),
+        void_reason: Some(reason),
+        version: before.version + 1,
+        ..before.clone()
+    };
+    let audit_record = AuditRecord {
+        action: AuditAction::Void,
+        table_number,
+        item_id,
+        before: Some(&before),
+        after: Some(&after),
+    };
+    conn.exec_drop(generate_void_query(), (voided_on.naive_utc(), reason.as_str(), table_number, item_id,))
+        .and_then(|_| record_audit_entries(conn, request_id, actor, &[audit_record]))
+        .map_err(|e| {
+            error!(error = ?e, "Voiding item failed");
+            op_error(&e)
+        })?;
+    Ok(after)
+}
+
+fn restore_item(
+    conn: &mut Transaction,
+    request_id: &RequestId,
+    actor: &Actor,
+    table_number: u32,
+    item_id: u32,
+    precondition: &Precondition,
+) -> Result<TableItem, PersistenceError> {
+    let before = select_item_for_update(conn, table_number, item_id, precondition)?;
+    if before.voided_on.is_none() {
+        error!(item_id, "Item is NOT voided");
+        return Err(PersistenceError::ResourceConflict);
+    }
+
+    let after = TableItem {
+        voided_on: None,
+        void_reason: None,
+        version: before.version + 1,
+        ..before.clone()
+    };
+    let audit_record = AuditRecord {
+        action: AuditAction::Restore,
+        table_number,
+        item_id,
+        before: Some(&before),
+        after: Some(&after),
+    };
+    conn.exec_drop(generate_restore_query(), (table_number, item_id,))
+        .and_then(|_| record_audit_entries(conn, request_id, actor, &[audit_record]))
+        .map_err(|e| {
+            error!(error = ?e, "Restoring item failed");
+            op_error(&e)
+        })?;
+    Ok(after)
+}
+
+fn generate_success_response(item_id: u32) -> RemoveTableItemResponse {
+    RemoveTableItemResponse {
+        status: "success".to_string(),
+        message: format!("Removed item: {}", item_id),
+    }
+}
+
+fn generate_void_query() -> String {
+    "UPDATE table_items SET voided_on = ?, void_reason = ?, version = version + 1 WHERE table_number = ? and item_id = ?".to_string()
+}
+
+fn generate_restore_query() -> String {
+    "UPDATE table_items SET voided_on = NULL, void_reason = NULL, version = version + 1 WHERE table_number = ? and item_id = ?".to_string()
+}